@@ -0,0 +1,156 @@
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+
+use futures::TryStreamExt;
+use ros_monitor_lib::protocol::{self, Hello};
+use ros_monitor_lib::remote::tls::TlsAcceptor;
+use ros_monitor_lib::{remote, RosMonitor};
+use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream, UnixListener};
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::output::{Encoder, OutputFormat};
+
+pub struct TcpOptions {
+    pub listen: SocketAddr,
+    pub token_file: Option<PathBuf>,
+    // certificate chain and private key
+    pub tls: Option<(PathBuf, PathBuf)>,
+}
+
+pub fn daemon(node: &str, interval: u64, socket: &Path, tcp: Option<TcpOptions>, format: OutputFormat) {
+    // clients expect the framed hello, text formats would make them give up
+    if !matches!(format, OutputFormat::Bitcode | OutputFormat::Cbor | OutputFormat::MessagePack) {
+        eprintln!("the daemon can only serve bitcode, cbor or msgpack");
+        std::process::exit(1);
+    }
+    if std::os::unix::net::UnixStream::connect(socket).is_ok() {
+        eprintln!("another daemon is already listening on {}", socket.display());
+        std::process::exit(1);
+    }
+    if let Err(err) = protocol::prepare_socket(socket) {
+        eprintln!("unable to listen on {}: {}", socket.display(), err);
+        std::process::exit(1);
+    }
+
+    let hello = Hello::new(env!("CARGO_PKG_VERSION"));
+    let (monitor, feed) = RosMonitor::manual(Default::default());
+    feed.set_hello(hello.clone());
+
+    let node = node.to_owned();
+    std::thread::spawn(move || {
+        crate::discovery_loop(&node, interval, |ts, state, duration| {
+            feed.set_discovery_duration(duration);
+            feed.update(ts, state);
+        });
+    });
+
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    runtime.block_on(async move {
+        let listener = UnixListener::bind(socket).unwrap();
+        eprintln!("listening on {}", socket.display());
+
+        if let Some(tcp) = tcp {
+            tokio::spawn(listen_tcp(tcp, monitor.clone(), hello.clone(), format));
+        }
+
+        loop {
+            let (stream, _) = listener.accept().await.unwrap();
+            let monitor = monitor.clone();
+            let hello = hello.clone();
+            tokio::spawn(async move {
+                if let Err(err) = serve_client(stream, monitor, hello, format).await {
+                    eprintln!("client disconnected: {}", err);
+                }
+            });
+        }
+    });
+}
+
+async fn listen_tcp(options: TcpOptions, monitor: RosMonitor, hello: Hello, format: OutputFormat) {
+    let token = options.token_file.map(|path| match std::fs::read_to_string(&path) {
+        Ok(token) => token.trim().to_owned(),
+        Err(err) => {
+            eprintln!("unable to read token from {}: {}", path.display(), err);
+            std::process::exit(1);
+        }
+    });
+    let tls = options.tls.map(|(cert, key)| match remote::tls::acceptor(&cert, &key) {
+        Ok(acceptor) => acceptor,
+        Err(err) => {
+            eprintln!("unable to load TLS certificate: {}", err);
+            std::process::exit(1);
+        }
+    });
+
+    let listener = TcpListener::bind(options.listen).await.unwrap();
+    eprintln!("listening on {}{}", options.listen, if tls.is_some() { " (tls)" } else { "" });
+    if token.is_none() {
+        eprintln!("warning: no --token-file, accepting TCP clients without authentication");
+    }
+
+    loop {
+        let (stream, address) = listener.accept().await.unwrap();
+        let monitor = monitor.clone();
+        let hello = hello.clone();
+        let token = token.clone();
+        let tls = tls.clone();
+        tokio::spawn(async move {
+            if let Err(err) = serve_tcp_client(stream, tls, token.as_deref(), monitor, hello, format).await {
+                eprintln!("client {} disconnected: {}", address, err);
+            }
+        });
+    }
+}
+
+async fn serve_tcp_client(
+    stream: TcpStream,
+    tls: Option<TlsAcceptor>,
+    token: Option<&str>,
+    monitor: RosMonitor,
+    hello: Hello,
+    format: OutputFormat,
+) -> std::io::Result<()> {
+    stream.set_nodelay(true)?;
+    match tls {
+        Some(tls) => {
+            let mut stream = tls.accept(stream).await?;
+            authenticate(&mut stream, token).await?;
+            serve_client(stream, monitor, hello, format).await
+        }
+        None => {
+            let mut stream = stream;
+            authenticate(&mut stream, token).await?;
+            serve_client(stream, monitor, hello, format).await
+        }
+    }
+}
+
+async fn authenticate(stream: &mut (impl AsyncRead + Unpin), token: Option<&str>) -> std::io::Result<()> {
+    // don't let a silent client hold on to a connection forever
+    match tokio::time::timeout(std::time::Duration::from_secs(10), remote::authenticate(stream, token)).await {
+        Ok(Ok(())) => Ok(()),
+        Ok(Err(err)) => Err(std::io::Error::other(err)),
+        Err(_) => Err(std::io::ErrorKind::TimedOut.into()),
+    }
+}
+
+async fn serve_client(mut stream: impl AsyncWrite + Unpin, monitor: RosMonitor, hello: Hello, format: OutputFormat) -> std::io::Result<()> {
+    let mut encoder = Encoder::new(format);
+    stream.write_all(encoder.hello(&hello)).await?;
+
+    let Ok((_, events)) = monitor.subscribe_since(None) else { return Ok(()) };
+    let mut events = std::pin::pin!(events);
+
+    loop {
+        match events.try_next().await {
+            Ok(Some(event)) => stream.write_all(encoder.event(&event)).await?,
+            Ok(None) | Err(RecvError::Closed) => return Ok(()),
+            // the client starts over from the current state when it reconnects
+            Err(RecvError::Lagged(count)) => {
+                eprintln!("client lagged behind by {} events, disconnecting", count);
+                return Ok(());
+            }
+        }
+    }
+}
@@ -0,0 +1,82 @@
+use std::io::{BufReader, Write};
+use std::path::Path;
+
+use ros_monitor_lib::protocol::Hello;
+use ros_monitor_lib::snapshot::{Snapshot, SNAPSHOT_VERSION};
+use ros_monitor_lib::state::RosState;
+use ros_monitor_lib::types::DiscoveryEventWrapper;
+use serde::Deserialize;
+
+use crate::output::{Encoder, OutputFormat};
+use crate::pretty;
+
+// A value of the JSON output, see `print_events` and `snapshot`. Snapshots
+// first, since they'd also pass for an event of unknown type.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Value {
+    Snapshot(Snapshot),
+    Event(DiscoveryEventWrapper),
+}
+
+// Prints what changed between two graphs saved as JSON, either events from
+// `-f json` or a snapshot from `snapshot -f json`, as pretty lines,
+// or as events in `format` when given. Exits with 1 when they differ, like diff(1).
+pub fn diff(before: &Path, after: &Path, format: Option<OutputFormat>) {
+    let (before, _) = load(before);
+    let (after, ts) = load(after);
+    let events = after.changes(&before);
+
+    let mut stdout = std::io::stdout();
+    match format {
+        Some(format) => {
+            let mut encoder = Encoder::new(format);
+            stdout.write_all(encoder.hello(&Hello::new(env!("CARGO_PKG_VERSION")))).unwrap();
+            for (event, seq) in events.iter().cloned().zip(1..) {
+                stdout.write_all(encoder.event(&DiscoveryEventWrapper { ts, seq, event })).unwrap();
+            }
+        }
+        None => {
+            let color = pretty::use_color();
+            for event in &events {
+                if let Some(line) = pretty::event_line(event, crate::watch::is_update(&before, event), color) {
+                    stdout.write_all(line.as_bytes()).unwrap();
+                }
+            }
+        }
+    }
+    stdout.flush().unwrap();
+
+    if !events.is_empty() {
+        std::process::exit(1);
+    }
+}
+
+// Replays the saved events or loads the snapshot, returning the graph and the
+// time it was last updated.
+fn load(path: &Path) -> (RosState, u64) {
+    let fail = |err: &dyn std::fmt::Display| -> ! {
+        eprintln!("unable to load {}: {}", path.display(), err);
+        std::process::exit(2);
+    };
+
+    let file = std::fs::File::open(path).unwrap_or_else(|err| fail(&err));
+    let mut state = RosState::default();
+    let mut ts = 0;
+    for value in serde_json::Deserializer::from_reader(BufReader::new(file)).into_iter() {
+        match value.unwrap_or_else(|err| fail(&err)) {
+            Value::Snapshot(snapshot) if !snapshot.is_supported() => {
+                fail(&format!("snapshot version {} isn't supported, expected {}", snapshot.version, SNAPSHOT_VERSION));
+            }
+            Value::Snapshot(snapshot) => {
+                ts = snapshot.ts;
+                state = snapshot.state;
+            }
+            Value::Event(event) => {
+                ts = event.ts;
+                state.update(event.event);
+            }
+        }
+    }
+    (state, ts)
+}
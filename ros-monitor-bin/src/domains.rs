@@ -0,0 +1,50 @@
+use std::io::Write;
+
+use futures::StreamExt;
+use ros_monitor_lib::domains::MultiDomainMonitor;
+use ros_monitor_lib::protocol::{Hello, WireFormat};
+use ros_monitor_lib::RosMonitorConfig;
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::output::{Encoder, OutputFormat};
+
+// Monitors several domains, each discovered by a process of our own started
+// in that domain with the same node name and interval.
+pub fn monitor(domain_ids: &[u32], node: &str, interval: u64) -> MultiDomainMonitor {
+    let command = std::env::current_exe().unwrap();
+    let args = vec!["--node".into(), node.into(), "--interval".into(), interval.to_string().into()];
+    let config = RosMonitorConfig { format: WireFormat::Bitcode, args, ..Default::default() };
+    MultiDomainMonitor::with_config(command, domain_ids.iter().copied(), config)
+}
+
+// Prints the events of several domains.
+pub fn print_events(domain_ids: &[u32], node: &str, interval: u64, format: OutputFormat) {
+    if !matches!(format, OutputFormat::Json | OutputFormat::Pretty | OutputFormat::Yaml) {
+        eprintln!("events of several domains can only be printed as json, pretty or yaml");
+        std::process::exit(1);
+    }
+
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    runtime.block_on(async {
+        let monitor = monitor(domain_ids, node, interval);
+
+        let mut stdout = std::io::stdout();
+        let mut encoder = Encoder::new(format);
+        stdout.write_all(encoder.hello(&Hello::new(env!("CARGO_PKG_VERSION")))).unwrap();
+        stdout.flush().unwrap();
+
+        let events = monitor.subscribe().unwrap();
+        let mut events = std::pin::pin!(events);
+        while let Some(event) = events.next().await {
+            match event {
+                Ok(event) => {
+                    stdout.write_all(encoder.domain_event(&event)).unwrap();
+                    stdout.flush().unwrap();
+                }
+                Err(RecvError::Lagged(count)) => eprintln!("lagged behind by {} events", count),
+                // a domain whose monitor stopped just ends, the others go on
+                Err(RecvError::Closed) => {}
+            }
+        }
+    });
+}
@@ -1,72 +1,263 @@
 use std::io::Write;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
 
-use clap::{Parser, ValueEnum};
+use clap::{Parser, Subcommand};
+use output::{Encoder, OutputFormat};
+use ros_monitor_lib::filter::{self, EventFilter};
+use ros_monitor_lib::protocol::Hello;
 use ros_monitor_lib::state::RosState;
 use ros_monitor_lib::types::DiscoveryEventWrapper;
+use ros_monitor_lib::{name, telemetry};
 use state::RosStateProvider;
 
+#[cfg(unix)]
+mod daemon;
+mod diff;
+mod domains;
+mod mqtt;
+mod output;
+mod pretty;
+mod serve;
+mod snapshot;
 mod state;
+mod tree;
+mod tui;
+mod watch;
 
 #[derive(Parser, Debug)]
 #[command(disable_help_flag = true)]
 #[command(disable_version_flag = true)]
 #[command(version = env!("CARGO_PKG_VERSION"))]
 struct Arguments {
-    #[arg(global = true, long, help = "ROS2 node name", default_value = "/intrepid/_discovery")]
+    // not global, so that `watch --node` can filter the watched nodes
+    #[arg(long, help = "ROS2 node name, given before any subcommand", default_value = "/intrepid/_discovery", value_parser = parse_node)]
     node: String,
     #[arg(global = true, short, long, help = "graph update interval in milliseconds", default_value = "800")]
     interval: u64,
-    #[arg(global = true, short, long, help = "output format", default_value = "json")]
-    format: OutputFormat,
+    #[arg(global = true, short, long, help = "output format [default: json, bitcode for daemon]")]
+    format: Option<OutputFormat>,
+    #[arg(global = true, long, value_delimiter = ',', help = "comma separated ROS domain ids, several only when printing events or serving [default: $ROS_DOMAIN_ID]")]
+    domain: Vec<u32>,
+    #[arg(global = true, long, help = "log every graph change as a JSON record on stderr")]
+    log_events: bool,
     #[arg(global = true, short, long, help = "print this help message", action = clap::ArgAction::Help)]
     help: Option<bool>,
     #[arg(short = 'V', long, help = "print intrepid agent version", action = clap::ArgAction::Version)]
     version: Option<bool>,
+    #[command(subcommand)]
+    command: Option<Command>,
 }
 
-#[derive(Debug, Clone, Copy, ValueEnum)]
-enum OutputFormat {
-    Json,
-    Bitcode,
+#[derive(Subcommand, Debug)]
+enum Command {
+    #[command(about = "serve the graph as JSON over HTTP")]
+    Serve {
+        #[arg(long, help = "address to listen on", default_value = "127.0.0.1:8080")]
+        listen: SocketAddr,
+    },
+    #[command(about = "browse the graph interactively in the terminal")]
+    Tui,
+    #[command(about = "print the changes to the matching nodes, topics and services")]
+    Watch {
+        #[arg(long, help = "everything in this namespace and below, e.g. '/robot1'")]
+        namespace: Option<String>,
+        #[arg(long = "node", visible_alias = "nodes", help = "comma separated node name patterns, e.g. '/nav2/*'")]
+        nodes: Option<String>,
+        #[arg(long, visible_alias = "topic", help = "comma separated topic name patterns, e.g. '/robot*/odom'")]
+        topics: Option<String>,
+        #[arg(long, visible_alias = "service", help = "comma separated service name patterns")]
+        services: Option<String>,
+    },
+    #[command(about = "print the namespaces with their number of nodes, topics and services")]
+    Tree {
+        #[arg(long, help = "namespace to start from", default_value = "/")]
+        namespace: String,
+        #[arg(long, help = "fold deeper namespaces into their ancestor")]
+        depth: Option<usize>,
+    },
+    #[command(about = "print the current graph as a snapshot")]
+    Snapshot,
+    #[command(about = "compare two graphs saved as JSON events or snapshots")]
+    Diff {
+        #[arg(help = "graph before")]
+        before: PathBuf,
+        #[arg(help = "graph after")]
+        after: PathBuf,
+    },
+    #[command(about = "publish the graph to an MQTT broker as retained messages")]
+    Mqtt {
+        #[arg(long, help = "broker host", default_value = "localhost")]
+        host: String,
+        #[arg(long, help = "broker port", default_value = "1883")]
+        port: u16,
+        #[arg(long, help = "robot name used in the MQTT topics")]
+        robot: String,
+        #[arg(long, help = "MQTT topic prefix", default_value = "ros")]
+        prefix: String,
+    },
+    #[cfg(unix)]
+    #[command(about = "share one discovery node between clients over a unix socket")]
+    Daemon {
+        #[arg(long, help = "socket path [default: $XDG_RUNTIME_DIR/intrepid-ros-monitor-$ROS_DOMAIN_ID.sock, or under /tmp/intrepid-ros-monitor-$UID]")]
+        socket: Option<PathBuf>,
+        #[arg(long, help = "also accept clients from other machines on this address")]
+        tcp: Option<SocketAddr>,
+        #[arg(long, requires = "tcp", help = "file with the token TCP clients must present")]
+        token_file: Option<PathBuf>,
+        #[arg(long, requires_all = ["tcp", "tls_key"], help = "PEM certificate chain to serve TCP clients over TLS")]
+        tls_cert: Option<PathBuf>,
+        #[arg(long, requires = "tls_cert", help = "PEM private key of the TLS certificate")]
+        tls_key: Option<PathBuf>,
+    },
 }
 
-fn parse_name(node: &str) -> (&str, &str) {
-    let parts: Vec<&str> = node.split('/').filter(|s| !s.is_empty()).collect();
-    #[allow(clippy::get_first)]
-    (parts.get(0).copied().unwrap_or_default(), parts.get(1).copied().unwrap_or_default())
+fn parse_node(node: &str) -> Result<String, name::NameError> {
+    name::parse_node_name(node).map(|_| node.to_owned())
+}
+
+fn now() -> u64 {
+    std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis() as u64
+}
+
+fn create_node(node: &str) -> r2r::Node {
+    let (name, namespace) = name::parse_node_name(node).unwrap();
+    let ros2_ctx = r2r::Context::create().unwrap();
+    r2r::Node::create(ros2_ctx, &name, &namespace).unwrap()
+}
+
+fn discovery_loop(node: &str, interval: u64, mut on_update: impl FnMut(u64, RosState, Duration)) {
+    let ros2_node = create_node(node);
+
+    loop {
+        let started_at = Instant::now();
+        let new_state = RosState::from_ros(&ros2_node).unwrap();
+        on_update(now(), new_state, started_at.elapsed());
+        std::thread::sleep(Duration::from_millis(interval));
+    }
+}
+
+// Tells when discovery has settled: right after our node is created, DDS
+// discovery hasn't found the other participants yet, so the first passes only
+// see part of the graph. Settled once two passes in a row agree, or after
+// `MAX_PASSES` for a graph that never stops changing.
+#[derive(Default)]
+struct Settling {
+    previous: Option<RosState>,
+    passes: usize,
+    settled: bool,
+}
+
+impl Settling {
+    const MAX_PASSES: usize = 10;
+
+    fn update(&mut self, state: &RosState) -> bool {
+        if !self.settled {
+            self.passes += 1;
+            self.settled = self.previous.as_ref() == Some(state) || self.passes >= Self::MAX_PASSES;
+            self.previous = (!self.settled).then(|| state.clone());
+        }
+        self.settled
+    }
+}
+
+// The graph once discovery has settled, for the commands printing it once.
+fn settled_state(node: &str, interval: u64) -> (u64, RosState) {
+    let ros2_node = create_node(node);
+    let mut settling = Settling::default();
+
+    loop {
+        std::thread::sleep(Duration::from_millis(interval));
+        let state = RosState::from_ros(&ros2_node).unwrap();
+        if settling.update(&state) {
+            return (now(), state);
+        }
+    }
 }
 
 fn main() {
     let args = Arguments::parse();
-    let (name, namespace) = parse_name(&args.node);
 
-    let ros2_ctx = r2r::Context::create().unwrap();
-    let ros2_node = r2r::Node::create(ros2_ctx.clone(), name, namespace).unwrap();
+    if args.log_events {
+        use tracing_subscriber::filter::{LevelFilter, Targets};
+        use tracing_subscriber::prelude::*;
+
+        tracing_subscriber::registry()
+            .with(tracing_subscriber::fmt::layer().json().with_writer(std::io::stderr))
+            .with(Targets::new().with_target(telemetry::TARGET, LevelFilter::INFO))
+            .init();
+    }
+
+    match args.domain[..] {
+        [] => {}
+        // before any ROS context is created
+        [domain_id] => std::env::set_var("ROS_DOMAIN_ID", domain_id.to_string()),
+        _ => match args.command {
+            None => return domains::print_events(&args.domain, &args.node, args.interval, args.format.unwrap_or(OutputFormat::Json)),
+            Some(Command::Serve { listen }) => return serve::serve_domains(&args.domain, &args.node, args.interval, listen),
+            Some(_) => {
+                eprintln!("several domains are only supported when printing events and by serve");
+                std::process::exit(1);
+            }
+        },
+    }
+
+    match args.command {
+        None => print_events(&args),
+        Some(Command::Serve { listen }) => serve::serve(&args.node, args.interval, listen),
+        Some(Command::Tui) => tui::tui(&args.node, args.interval).unwrap(),
+        Some(Command::Watch { ref namespace, ref nodes, ref topics, ref services }) => {
+            // patterns replace those of the namespace for their kind
+            let namespace = namespace.as_deref().map(EventFilter::namespace).unwrap_or_default();
+            let filter = EventFilter {
+                nodes: nodes.as_deref().map(filter::parse_patterns).or(namespace.nodes),
+                topics: topics.as_deref().map(filter::parse_patterns).or(namespace.topics),
+                services: services.as_deref().map(filter::parse_patterns).or(namespace.services),
+            };
+            watch::watch(&args.node, args.interval, filter, args.format);
+        }
+        Some(Command::Tree { ref namespace, depth }) => tree::tree(&args.node, args.interval, namespace, depth, args.format),
+        Some(Command::Snapshot) => snapshot::snapshot(&args.node, args.interval, args.format.unwrap_or(OutputFormat::Json)),
+        Some(Command::Diff { ref before, ref after }) => diff::diff(before, after, args.format),
+        Some(Command::Mqtt { ref host, port, ref robot, ref prefix }) => {
+            let options = mqtt::BridgeOptions { host: host.clone(), port, robot: robot.clone(), prefix: prefix.clone() };
+            mqtt::mqtt(&args.node, args.interval, options);
+        }
+        #[cfg(unix)]
+        Some(Command::Daemon { ref socket, tcp, ref token_file, ref tls_cert, ref tls_key }) => {
+            let socket = socket.clone().unwrap_or_else(ros_monitor_lib::protocol::default_socket_path);
+            let tcp = tcp.map(|listen| daemon::TcpOptions {
+                listen,
+                token_file: token_file.clone(),
+                tls: tls_cert.clone().zip(tls_key.clone()),
+            });
+            daemon::daemon(&args.node, args.interval, &socket, tcp, args.format.unwrap_or(OutputFormat::Bitcode));
+        }
+    }
+}
+
+fn print_events(args: &Arguments) {
     let mut state = RosState::default();
+    let mut seq = 0;
     let mut stdout = std::io::stdout();
-    let mut bitcode_buffer = bitcode::Buffer::new();
+    let mut encoder = Encoder::new(args.format.unwrap_or(OutputFormat::Json));
 
-    loop {
-        let new_state = RosState::from_ros(&ros2_node).unwrap();
-        let ts = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis() as u64;
+    stdout.write_all(encoder.hello(&Hello::new(env!("CARGO_PKG_VERSION")))).unwrap();
+    stdout.flush().unwrap();
+
+    discovery_loop(&args.node, args.interval, |ts, new_state, _| {
         let events = new_state.changes(&state);
         state = new_state;
+        if !events.is_empty() {
+            stdout.write_all(encoder.table(&state)).unwrap();
+        }
         for event in events {
-            let event = DiscoveryEventWrapper { ts, event };
-            match args.format {
-                OutputFormat::Json => {
-                    stdout.write_all(&serde_json::to_vec(&event).unwrap()).unwrap();
-                    stdout.write_all(b"\n").unwrap();
-                }
-                OutputFormat::Bitcode => {
-                    let buffer = bitcode_buffer.encode(&event);
-                    let buffer_size = buffer.len();
-                    stdout.write_all(&(buffer_size as u32).to_le_bytes()).unwrap();
-                    stdout.write_all(buffer).unwrap();
-                }
-            }
+            seq += 1;
+            let event = DiscoveryEventWrapper { ts, seq, event };
+            telemetry::record_event(&event);
+            stdout.write_all(encoder.event(&event)).unwrap();
             stdout.flush().unwrap();
         }
-        std::thread::sleep(std::time::Duration::from_millis(args.interval));
-    }
+    });
 }
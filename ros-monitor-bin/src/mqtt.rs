@@ -0,0 +1,211 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use futures::StreamExt;
+use ros_monitor_lib::filter::fully_qualified_name;
+use ros_monitor_lib::protocol::Hello;
+use ros_monitor_lib::types::DiscoveryEvent;
+use ros_monitor_lib::RosMonitor;
+use rumqttc::{AsyncClient, Event, LastWill, MqttOptions, Packet, QoS};
+use tokio::sync::mpsc;
+
+// Publishes the graph under `{prefix}/{robot}/graph`:
+//
+//   .../status                  `online`, or `offline` as the last will
+//   .../nodes/{ns}/{name}       node_added event
+//   .../topics/{name}           topic_added event
+//   .../services/{name}         service_added event
+//
+// Entities are retained, so a new subscriber gets the current graph, and
+// cleared with an empty retained message (tombstone) when removed.
+pub struct BridgeOptions {
+    pub host: String,
+    pub port: u16,
+    pub robot: String,
+    pub prefix: String,
+}
+
+enum Notification {
+    Connected,
+    // retained message found on the broker
+    Retained(String),
+}
+
+pub fn mqtt(node: &str, interval: u64, options: BridgeOptions) {
+    let (monitor, feed) = RosMonitor::manual(Default::default());
+    feed.set_hello(Hello::new(env!("CARGO_PKG_VERSION")));
+
+    let (ready_tx, ready) = tokio::sync::oneshot::channel();
+    let node = node.to_owned();
+    std::thread::spawn(move || {
+        let mut ready_tx = Some(ready_tx);
+        let mut settling = crate::Settling::default();
+        crate::discovery_loop(&node, interval, |ts, state, duration| {
+            let settled = settling.update(&state);
+            feed.set_discovery_duration(duration);
+            feed.update(ts, state);
+            if let Some(ready_tx) = ready_tx.take_if(|_| settled) {
+                let _ = ready_tx.send(());
+            }
+        });
+    });
+
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    runtime.block_on(async move {
+        // otherwise entities retained by a previous run would be cleared before
+        // being discovered again, see `Settling`
+        let _ = ready.await;
+
+        let base = graph_topic(&options.prefix, &options.robot);
+        let status = format!("{}/status", base);
+
+        let mut mqtt_options = MqttOptions::new(format!("intrepid-ros-monitor-{}", options.robot), &options.host, options.port);
+        mqtt_options.set_keep_alive(Duration::from_secs(10));
+        mqtt_options.set_last_will(LastWill::new(&status, "offline", QoS::AtLeastOnce, true));
+        let (client, mut eventloop) = AsyncClient::new(mqtt_options, 64);
+
+        let (notifications, notifications_rx) = mpsc::unbounded_channel();
+        tokio::spawn(publish_graph(monitor, client, base, notifications_rx));
+
+        loop {
+            match eventloop.poll().await {
+                Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                    eprintln!("connected to mqtt://{}:{}", options.host, options.port);
+                    let _ = notifications.send(Notification::Connected);
+                }
+                Ok(Event::Incoming(Packet::Publish(publish))) if publish.retain && !publish.payload.is_empty() => {
+                    let _ = notifications.send(Notification::Retained(publish.topic));
+                }
+                Ok(_) => {}
+                Err(err) => {
+                    // the next poll reconnects
+                    eprintln!("mqtt connection error: {}", err);
+                    tokio::time::sleep(Duration::from_secs(1)).await;
+                }
+            }
+        }
+    });
+}
+
+async fn publish_graph(
+    monitor: RosMonitor,
+    client: AsyncClient,
+    base: String,
+    mut notifications: mpsc::UnboundedReceiver<Notification>,
+) -> Result<(), rumqttc::ClientError> {
+    let events = monitor.subscribe_filtered(Default::default());
+    let mut events = std::pin::pin!(events);
+    // what we currently have retained on the broker
+    let mut retained = HashMap::new();
+
+    loop {
+        tokio::select! {
+            event = events.next() => {
+                let Some(event) = event else { return Ok(()) };
+                let Some((topic, payload)) = entity_message(&base, &event) else { continue };
+
+                if payload.is_empty() {
+                    retained.remove(&topic);
+                } else {
+                    retained.insert(topic.clone(), payload.clone());
+                }
+                client.publish(topic, QoS::AtLeastOnce, true, payload).await?;
+            }
+            notification = notifications.recv() => match notification {
+                // the broker may have lost retained messages while we were disconnected
+                Some(Notification::Connected) => {
+                    client.publish(format!("{}/status", base), QoS::AtLeastOnce, true, "online").await?;
+                    for (topic, payload) in &retained {
+                        client.publish(topic, QoS::AtLeastOnce, true, payload.clone()).await?;
+                    }
+                    for kind in ["nodes", "topics", "services"] {
+                        client.subscribe(format!("{}/{}/#", base, kind), QoS::AtLeastOnce).await?;
+                    }
+                }
+                // left behind by a previous run, for an entity that's gone since
+                Some(Notification::Retained(topic)) => {
+                    if !retained.contains_key(&topic) {
+                        client.publish(topic, QoS::AtLeastOnce, true, Vec::new()).await?;
+                    }
+                }
+                None => return Ok(()),
+            }
+        }
+    }
+}
+
+fn graph_topic(prefix: &str, robot: &str) -> String {
+    format!("{}/{}/graph", prefix, robot)
+}
+
+// The retained message for the entity of `event`: the event as JSON when the
+// entity is added, an empty tombstone when it's removed.
+fn entity_message(base: &str, event: &DiscoveryEvent) -> Option<(String, Vec<u8>)> {
+    let (topic, added) = entity_topic(base, event)?;
+    let payload = if added { serde_json::to_vec(event).unwrap() } else { Vec::new() };
+    Some((topic, payload))
+}
+
+fn entity_topic(base: &str, event: &DiscoveryEvent) -> Option<(String, bool)> {
+    match event {
+        DiscoveryEvent::NodeAdded { name, namespace, .. } => {
+            Some((format!("{}/nodes{}", base, fully_qualified_name(namespace, name)), true))
+        }
+        DiscoveryEvent::NodeRemoved { name, namespace } => {
+            Some((format!("{}/nodes{}", base, fully_qualified_name(namespace, name)), false))
+        }
+        DiscoveryEvent::TopicAdded { name, .. } => Some((format!("{}/topics{}", base, name), true)),
+        DiscoveryEvent::TopicRemoved { name } => Some((format!("{}/topics{}", base, name), false)),
+        DiscoveryEvent::ServiceAdded { name, .. } => Some((format!("{}/services{}", base, name), true)),
+        DiscoveryEvent::ServiceRemoved { name } => Some((format!("{}/services{}", base, name), false)),
+        DiscoveryEvent::Ping | DiscoveryEvent::Unknown => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ros_monitor_lib::types::{ServiceProperties, TopicProperties};
+
+    use super::*;
+
+    const BASE: &str = "ros/robot1/graph";
+
+    fn topic(name: &str) -> DiscoveryEvent {
+        DiscoveryEvent::TopicAdded {
+            name: name.to_owned(),
+            properties: TopicProperties { types: vec!["nav_msgs/msg/Odometry".to_owned()], publishers: vec![], subscribers: vec![] },
+        }
+    }
+
+    #[test]
+    fn base_topic() {
+        assert_eq!(graph_topic("ros", "robot1"), BASE);
+    }
+
+    #[test]
+    fn entity_topics() {
+        let node = |namespace: &str| DiscoveryEvent::NodeRemoved { name: "talker".to_owned(), namespace: namespace.to_owned() };
+        assert_eq!(entity_topic(BASE, &node("/")), Some(("ros/robot1/graph/nodes/talker".to_owned(), false)));
+        assert_eq!(entity_topic(BASE, &node("/robot1/demo")), Some(("ros/robot1/graph/nodes/robot1/demo/talker".to_owned(), false)));
+        assert_eq!(entity_topic(BASE, &topic("/robot1/odom")), Some(("ros/robot1/graph/topics/robot1/odom".to_owned(), true)));
+        let service = DiscoveryEvent::ServiceAdded { name: "/talker/get_parameters".to_owned(), properties: ServiceProperties { types: vec![] } };
+        assert_eq!(entity_topic(BASE, &service), Some(("ros/robot1/graph/services/talker/get_parameters".to_owned(), true)));
+        assert_eq!(entity_topic(BASE, &DiscoveryEvent::Ping), None);
+        assert_eq!(entity_topic(BASE, &DiscoveryEvent::Unknown), None);
+    }
+
+    #[test]
+    fn added_entity_is_retained_as_json() {
+        let event = topic("/odom");
+        let (topic, payload) = entity_message(BASE, &event).unwrap();
+        assert_eq!(topic, "ros/robot1/graph/topics/odom");
+        assert_eq!(serde_json::from_slice::<DiscoveryEvent>(&payload).unwrap(), event);
+        assert_eq!(serde_json::from_slice::<serde_json::Value>(&payload).unwrap()["type"], "topic_added");
+    }
+
+    #[test]
+    fn removed_entity_is_a_tombstone() {
+        let event = DiscoveryEvent::TopicRemoved { name: "/odom".to_owned() };
+        assert_eq!(entity_message(BASE, &event), Some(("ros/robot1/graph/topics/odom".to_owned(), vec![])));
+    }
+}
@@ -0,0 +1,229 @@
+use clap::ValueEnum;
+use ros_monitor_lib::filter::fully_qualified_name;
+use ros_monitor_lib::protocol::{FrameWriter, Hello, WireFormat};
+use ros_monitor_lib::state::RosState;
+use ros_monitor_lib::types::{self, DiscoveryEvent, DiscoveryEventWrapper, DomainEventWrapper};
+use serde::Serialize;
+
+use crate::pretty;
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum OutputFormat {
+    Json,
+    // aligned and colourised lines
+    Pretty,
+    // the whole graph, redrawn on every change
+    Table,
+    Yaml,
+    // one row per endpoint
+    Csv,
+    Bitcode,
+    Cbor,
+    #[value(name = "msgpack")]
+    MessagePack,
+}
+
+// Serializes the hello and events in the given format, into a buffer
+// reused between calls. Binary formats are framed, see `protocol`.
+pub struct Encoder {
+    format: OutputFormat,
+    writer: FrameWriter<Vec<u8>>,
+    color: bool,
+}
+
+impl Encoder {
+    pub fn new(format: OutputFormat) -> Self {
+        let wire_format = match format {
+            OutputFormat::Cbor => WireFormat::Cbor,
+            OutputFormat::MessagePack => WireFormat::MessagePack,
+            _ => WireFormat::Bitcode,
+        };
+        Self { format, writer: FrameWriter::with_format(Vec::new(), wire_format), color: pretty::use_color() }
+    }
+
+    pub fn hello(&mut self, hello: &Hello) -> &[u8] {
+        self.writer.get_mut().clear();
+        match self.format {
+            // only events, one per line or document, the handshake is for
+            // the library reading the binary formats
+            OutputFormat::Json | OutputFormat::Yaml => {}
+            OutputFormat::Pretty => {
+                self.writer.get_mut().extend_from_slice(pretty::hello_line(hello, self.color).as_bytes());
+            }
+            // rendered from the state instead, see `Encoder::table`
+            OutputFormat::Table => {}
+            OutputFormat::Csv => {
+                let mut csv = csv::Writer::from_writer(self.writer.get_mut());
+                csv.write_record(CSV_HEADER).unwrap();
+                csv.flush().unwrap();
+            }
+            OutputFormat::Bitcode | OutputFormat::Cbor | OutputFormat::MessagePack => {
+                self.writer.write_hello(hello).unwrap();
+            }
+        }
+        self.writer.get_ref()
+    }
+
+    pub fn event(&mut self, event: &DiscoveryEventWrapper) -> &[u8] {
+        self.writer.get_mut().clear();
+        match self.format {
+            OutputFormat::Json => {
+                serde_json::to_writer(self.writer.get_mut(), event).unwrap();
+                self.writer.get_mut().push(b'\n');
+            }
+            OutputFormat::Pretty => {
+                if let Some(line) = pretty::event_line(&event.event, false, self.color) {
+                    self.writer.get_mut().extend_from_slice(line.as_bytes());
+                }
+            }
+            OutputFormat::Table => {}
+            OutputFormat::Yaml => {
+                self.writer.get_mut().extend_from_slice(b"---\n");
+                serde_yaml::to_writer(self.writer.get_mut(), event).unwrap();
+            }
+            OutputFormat::Csv => {
+                let mut csv = csv::WriterBuilder::new().has_headers(false).from_writer(self.writer.get_mut());
+                for row in csv_rows(event) {
+                    csv.serialize(row).unwrap();
+                }
+                csv.flush().unwrap();
+            }
+            OutputFormat::Bitcode | OutputFormat::Cbor | OutputFormat::MessagePack => {
+                self.writer.write_event(event).unwrap();
+            }
+        }
+        self.writer.get_ref()
+    }
+
+    // Like `event`, tagged with the domain for the formats that have room for it.
+    pub fn domain_event(&mut self, event: &DomainEventWrapper) -> &[u8] {
+        match self.format {
+            OutputFormat::Json => {
+                self.writer.get_mut().clear();
+                serde_json::to_writer(self.writer.get_mut(), event).unwrap();
+                self.writer.get_mut().push(b'\n');
+            }
+            OutputFormat::Pretty => {
+                self.writer.get_mut().clear();
+                if let Some(line) = pretty::event_line(&event.event.event, false, self.color) {
+                    let domain = pretty::domain_label(event.domain_id, self.color);
+                    self.writer.get_mut().extend_from_slice(format!("{} {}", domain, line).as_bytes());
+                }
+            }
+            OutputFormat::Yaml => {
+                self.writer.get_mut().clear();
+                self.writer.get_mut().extend_from_slice(b"---\n");
+                serde_yaml::to_writer(self.writer.get_mut(), event).unwrap();
+            }
+            _ => {
+                self.event(&event.event);
+            }
+        }
+        self.writer.get_ref()
+    }
+
+    // The whole graph, for formats that show it rather than the changes.
+    pub fn table(&mut self, state: &RosState) -> &[u8] {
+        self.writer.get_mut().clear();
+        if let OutputFormat::Table = self.format {
+            if self.color {
+                // clear the screen and redraw from the top
+                self.writer.get_mut().extend_from_slice(b"\x1b[H\x1b[2J");
+            }
+            let table = pretty::table(state, pretty::terminal_width(), self.color);
+            self.writer.get_mut().extend_from_slice(table.as_bytes());
+        }
+        self.writer.get_ref()
+    }
+}
+
+const CSV_HEADER: [&str; 11] = [
+    "ts",
+    "event",
+    "entity",
+    "endpoint",
+    "node",
+    "endpoint_name",
+    "type",
+    "history",
+    "depth",
+    "reliability",
+    "durability",
+];
+
+// Field order must match `CSV_HEADER`. `entity` is the name of the node, topic
+// or service the event is about, `endpoint_name` the topic or service of the endpoint.
+#[derive(Debug, Default, Serialize)]
+struct CsvRow<'a> {
+    ts: u64,
+    event: &'a str,
+    entity: String,
+    endpoint: &'a str,
+    node: String,
+    endpoint_name: &'a str,
+    r#type: &'a str,
+    history: Option<types::HistoryPolicy>,
+    depth: Option<usize>,
+    reliability: Option<types::ReliabilityPolicy>,
+    durability: Option<types::DurabilityPolicy>,
+}
+
+fn csv_rows(event: &DiscoveryEventWrapper) -> Vec<CsvRow<'_>> {
+    let row = |entity: String| CsvRow { ts: event.ts, event: event.event.kind(), entity, ..Default::default() };
+    let mut rows = vec![];
+
+    match &event.event {
+        DiscoveryEvent::NodeAdded { name, namespace, properties } => {
+            let node = fully_qualified_name(namespace, name);
+            let endpoints = [
+                ("publisher", &properties.publishers),
+                ("subscriber", &properties.subscribers),
+                ("client", &properties.clients),
+                ("service", &properties.services),
+            ];
+            for (endpoint, names) in endpoints {
+                for (endpoint_name, r#type) in names {
+                    rows.push(CsvRow { endpoint, node: node.clone(), endpoint_name, r#type, ..row(node.clone()) });
+                }
+            }
+            if rows.is_empty() {
+                rows.push(row(node));
+            }
+        }
+        DiscoveryEvent::TopicAdded { name, properties } => {
+            let endpoints = [("publisher", &properties.publishers), ("subscriber", &properties.subscribers)];
+            for (endpoint, endpoints) in endpoints {
+                for properties in endpoints {
+                    let qos = &properties.qos_profile;
+                    rows.push(CsvRow {
+                        endpoint,
+                        node: fully_qualified_name(&properties.node_namespace, &properties.node_name),
+                        endpoint_name: name,
+                        r#type: &properties.topic_type,
+                        history: Some(qos.history),
+                        depth: Some(qos.depth),
+                        reliability: Some(qos.reliability),
+                        durability: Some(qos.durability),
+                        ..row(name.clone())
+                    });
+                }
+            }
+            if rows.is_empty() {
+                rows.push(row(name.clone()));
+            }
+        }
+        DiscoveryEvent::ServiceAdded { name, properties } => {
+            for r#type in &properties.types {
+                rows.push(CsvRow { endpoint: "service", endpoint_name: name, r#type, ..row(name.clone()) });
+            }
+            if rows.is_empty() {
+                rows.push(row(name.clone()));
+            }
+        }
+        DiscoveryEvent::NodeRemoved { name, namespace } => rows.push(row(fully_qualified_name(namespace, name))),
+        DiscoveryEvent::TopicRemoved { name } | DiscoveryEvent::ServiceRemoved { name } => rows.push(row(name.clone())),
+        DiscoveryEvent::Ping | DiscoveryEvent::Unknown => {}
+    }
+
+    rows
+}
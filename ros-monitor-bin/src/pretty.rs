@@ -0,0 +1,244 @@
+use std::fmt::Write;
+use std::io::IsTerminal;
+
+use ros_monitor_lib::filter::fully_qualified_name;
+use ros_monitor_lib::namespace::NamespaceTree;
+use ros_monitor_lib::protocol::Hello;
+use ros_monitor_lib::state::RosState;
+use ros_monitor_lib::types::DiscoveryEvent;
+
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "\x1b[1m";
+const DIM: &str = "\x1b[2m";
+const RED: &str = "\x1b[31m";
+const GREEN: &str = "\x1b[32m";
+const YELLOW: &str = "\x1b[33m";
+const CYAN: &str = "\x1b[36m";
+
+// Colours only when writing to a terminal, and unless disabled with NO_COLOR.
+pub fn use_color() -> bool {
+    std::io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none_or(|value| value.is_empty())
+}
+
+pub fn terminal_width() -> usize {
+    terminal_size::terminal_size().map_or(120, |(width, _)| width.0 as usize)
+}
+
+struct Style(bool);
+
+impl Style {
+    fn paint(&self, color: &str, text: &str) -> String {
+        if self.0 {
+            format!("{}{}{}", color, text, RESET)
+        } else {
+            text.to_owned()
+        }
+    }
+}
+
+pub fn hello_line(hello: &Hello, color: bool) -> String {
+    let mut line = format!("intrepid-ros-monitor {}, protocol {}", hello.binary_version, hello.protocol_version);
+    if !hello.ros_distro.is_empty() {
+        let _ = write!(line, ", ROS {}", hello.ros_distro);
+    }
+    if !hello.rmw_implementation.is_empty() {
+        let _ = write!(line, " ({})", hello.rmw_implementation);
+    }
+    format!("{}\n", Style(color).paint(DIM, &format!("# {}", line)))
+}
+
+// e.g. `+ topic   /cmd_vel [geometry_msgs/msg/Twist] pubs=1 subs=2`, marked
+// with `~` instead of `+` when `updated` (added again with new properties).
+pub fn event_line(event: &DiscoveryEvent, updated: bool, color: bool) -> Option<String> {
+    let style = Style(color);
+    let (sign, kind, name, details) = match event {
+        DiscoveryEvent::NodeAdded { name, namespace, properties } => {
+            let details = format!(
+                "pubs={} subs={} clients={} services={}",
+                properties.publishers.len(),
+                properties.subscribers.len(),
+                properties.clients.len(),
+                properties.services.len(),
+            );
+            ('+', "node", fully_qualified_name(namespace, name), style.paint(DIM, &details))
+        }
+        DiscoveryEvent::TopicAdded { name, properties } => {
+            let details = format!(
+                "{} {}",
+                style.paint(CYAN, &format!("[{}]", properties.types.join(", "))),
+                style.paint(DIM, &format!("pubs={} subs={}", properties.publishers.len(), properties.subscribers.len())),
+            );
+            ('+', "topic", name.clone(), details)
+        }
+        DiscoveryEvent::ServiceAdded { name, properties } => {
+            ('+', "service", name.clone(), style.paint(CYAN, &format!("[{}]", properties.types.join(", "))))
+        }
+        DiscoveryEvent::NodeRemoved { name, namespace } => ('-', "node", fully_qualified_name(namespace, name), String::new()),
+        DiscoveryEvent::TopicRemoved { name } => ('-', "topic", name.clone(), String::new()),
+        DiscoveryEvent::ServiceRemoved { name } => ('-', "service", name.clone(), String::new()),
+        DiscoveryEvent::Ping | DiscoveryEvent::Unknown => return None,
+    };
+
+    let (sign, color) = match sign {
+        '+' if updated => ('~', YELLOW),
+        '+' => ('+', GREEN),
+        _ => (sign, RED),
+    };
+    let prefix = style.paint(color, &format!("{} {:<7}", sign, kind));
+    let line = format!("{} {} {}", prefix, style.paint(BOLD, &name), details);
+    Some(format!("{}\n", line.trim_end()))
+}
+
+// e.g. `[domain 5]`
+pub fn domain_label(domain_id: u32, color: bool) -> String {
+    Style(color).paint(CYAN, &format!("[domain {}]", domain_id))
+}
+
+// Milliseconds since the epoch as an RFC 3339 UTC timestamp.
+pub fn timestamp(ts: u64, color: bool) -> String {
+    let (days, millis) = (ts / 86_400_000, ts % 86_400_000);
+    let (hours, minutes, seconds) = (millis / 3_600_000, millis / 60_000 % 60, millis / 1000 % 60);
+
+    // civil from days, see http://howardhinnant.github.io/date_algorithms.html
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    let timestamp = format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year, month, day, hours, minutes, seconds, millis % 1000,
+    );
+    Style(color).paint(DIM, &timestamp)
+}
+
+// e.g.
+//   /              nodes=3 topics=12 services=20
+//   ├─ robot1      nodes=2 topics=6 services=10
+//   │  └─ nav2     nodes=1 topics=2 services=5
+//   └─ robot2      nodes=1 topics=6 services=10
+pub fn namespace_tree(tree: &NamespaceTree, color: bool) -> String {
+    fn lines(tree: &NamespaceTree, label: String, indent: &str, output: &mut Vec<(String, String)>) {
+        output.push((label, format!("nodes={} topics={} services={}", tree.nodes, tree.topics, tree.services)));
+        let mut children = tree.children.iter().peekable();
+        while let Some((segment, child)) = children.next() {
+            let last = children.peek().is_none();
+            let label = format!("{}{}{}", indent, if last { "└─ " } else { "├─ " }, segment);
+            lines(child, label, &format!("{}{}", indent, if last { "   " } else { "│  " }), output);
+        }
+    }
+
+    let style = Style(color);
+    let mut rows = vec![];
+    lines(tree, tree.namespace.clone(), "", &mut rows);
+    let width = rows.iter().map(|(label, _)| label.chars().count()).max().unwrap_or(0);
+
+    let mut output = String::new();
+    for (label, counts) in rows {
+        let padding = width - label.chars().count();
+        let _ = writeln!(output, "{}{}  {}", style.paint(BOLD, &label), " ".repeat(padding), style.paint(DIM, &counts));
+    }
+    output
+}
+
+// The whole graph as one table per entity kind, fitted to `width` columns.
+pub fn table(state: &RosState, width: usize, color: bool) -> String {
+    let style = Style(color);
+    let mut output = String::new();
+
+    let nodes: Vec<_> = state.nodes.iter()
+        .map(|((namespace, name), properties)| {
+            let participant = properties.participant.as_ref();
+            vec![
+                fully_qualified_name(namespace, name),
+                participant.and_then(|participant| participant.host_label()).unwrap_or_default(),
+                participant.and_then(|participant| participant.pid).map(|pid| pid.to_string()).unwrap_or_default(),
+                properties.publishers.len().to_string(),
+                properties.subscribers.len().to_string(),
+                properties.clients.len().to_string(),
+                properties.services.len().to_string(),
+            ]
+        })
+        .collect();
+    let headers = ["NODE", "HOST", "PID", "PUBS", "SUBS", "CLIENTS", "SERVICES"];
+    render_table(&mut output, &style, &headers, &[false, false, true, true, true, true, true], nodes, width);
+
+    let topics: Vec<_> = state.topics.iter()
+        .map(|(name, properties)| {
+            vec![
+                name.clone(),
+                properties.types.join(", "),
+                properties.publishers.len().to_string(),
+                properties.subscribers.len().to_string(),
+            ]
+        })
+        .collect();
+    output.push('\n');
+    render_table(&mut output, &style, &["TOPIC", "TYPE", "PUBS", "SUBS"], &[false, false, true, true], topics, width);
+
+    let services: Vec<_> = state.services.iter()
+        .map(|(name, properties)| vec![name.clone(), properties.types.join(", ")])
+        .collect();
+    output.push('\n');
+    render_table(&mut output, &style, &["SERVICE", "TYPE"], &[false, false], services, width);
+
+    output
+}
+
+fn render_table(output: &mut String, style: &Style, headers: &[&str], right_aligned: &[bool], rows: Vec<Vec<String>>, width: usize) {
+    const SEPARATOR: usize = 2;
+    const MIN_WIDTH: usize = 8;
+
+    let mut widths: Vec<usize> = headers.iter().map(|header| header.len()).collect();
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    // shrink the widest text columns until the table fits
+    let total = |widths: &[usize]| widths.iter().sum::<usize>() + SEPARATOR * (widths.len() - 1);
+    while total(&widths) > width {
+        let widest = (0..widths.len())
+            .filter(|&i| !right_aligned[i] && widths[i] > MIN_WIDTH)
+            .max_by_key(|&i| widths[i]);
+        let Some(widest) = widest else { break };
+        widths[widest] -= 1;
+    }
+
+    let header: Vec<String> = headers.iter().map(|header| header.to_string()).collect();
+    let line = format_row(&header, &widths, right_aligned);
+    let _ = writeln!(output, "{}", style.paint(BOLD, &line));
+    for row in &rows {
+        let _ = writeln!(output, "{}", format_row(row, &widths, right_aligned));
+    }
+}
+
+fn format_row(row: &[String], widths: &[usize], right_aligned: &[bool]) -> String {
+    let cells: Vec<String> = row.iter().zip(widths).zip(right_aligned)
+        .map(|((cell, &width), &right_aligned)| {
+            let cell = truncate(cell, width);
+            if right_aligned {
+                format!("{:>width$}", cell)
+            } else {
+                format!("{:<width$}", cell)
+            }
+        })
+        .collect();
+    cells.join("  ").trim_end().to_owned()
+}
+
+fn truncate(text: &str, width: usize) -> String {
+    if text.chars().count() <= width {
+        text.to_owned()
+    } else {
+        let mut truncated: String = text.chars().take(width.saturating_sub(1)).collect();
+        truncated.push('…');
+        truncated
+    }
+}
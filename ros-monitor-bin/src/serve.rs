@@ -0,0 +1,35 @@
+use std::net::SocketAddr;
+
+use ros_monitor_lib::protocol::Hello;
+use ros_monitor_lib::{server, RosMonitor};
+
+pub fn serve(node: &str, interval: u64, listen: SocketAddr) {
+    let (monitor, feed) = RosMonitor::manual(Default::default());
+    feed.set_hello(Hello::new(env!("CARGO_PKG_VERSION")));
+
+    let node = node.to_owned();
+    std::thread::spawn(move || {
+        crate::discovery_loop(&node, interval, |ts, state, duration| {
+            feed.set_discovery_duration(duration);
+            feed.update(ts, state);
+        });
+    });
+
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    runtime.block_on(async move {
+        let listener = tokio::net::TcpListener::bind(listen).await.unwrap();
+        eprintln!("listening on http://{}", listener.local_addr().unwrap());
+        axum::serve(listener, server::router(monitor)).await.unwrap();
+    });
+}
+
+// Serves the graphs of several domains, see `server::domains_router`.
+pub fn serve_domains(domain_ids: &[u32], node: &str, interval: u64, listen: SocketAddr) {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    runtime.block_on(async move {
+        let monitor = crate::domains::monitor(domain_ids, node, interval);
+        let listener = tokio::net::TcpListener::bind(listen).await.unwrap();
+        eprintln!("listening on http://{}", listener.local_addr().unwrap());
+        axum::serve(listener, server::domains_router(monitor)).await.unwrap();
+    });
+}
@@ -0,0 +1,26 @@
+use std::io::Write;
+
+use ros_monitor_lib::snapshot::Snapshot;
+
+use crate::output::OutputFormat;
+
+// Prints the graph once discovery has settled, as a snapshot to be reloaded
+// later, e.g. by `diff`.
+pub fn snapshot(node: &str, interval: u64, format: OutputFormat) {
+    if !matches!(format, OutputFormat::Json | OutputFormat::Yaml) {
+        eprintln!("snapshots can only be written as json or yaml");
+        std::process::exit(1);
+    }
+
+    let (ts, state) = crate::settled_state(node, interval);
+    let snapshot = Snapshot::new(ts, state);
+    let mut stdout = std::io::stdout();
+    match format {
+        OutputFormat::Json => {
+            serde_json::to_writer_pretty(&mut stdout, &snapshot).unwrap();
+            stdout.write_all(b"\n").unwrap();
+        }
+        _ => serde_yaml::to_writer(&mut stdout, &snapshot).unwrap(),
+    }
+    stdout.flush().unwrap();
+}
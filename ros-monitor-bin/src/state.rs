@@ -1,7 +1,8 @@
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 
+use ros_monitor_lib::diagnostic::{Diagnostic, EndpointKind};
 use ros_monitor_lib::state::RosState;
-use ros_monitor_lib::types;
+use ros_monitor_lib::{participant, types};
 
 pub trait RosStateProvider {
     fn from_ros(node: &r2r::Node) -> Result<RosState, r2r::Error>;
@@ -9,70 +10,85 @@ pub trait RosStateProvider {
 
 impl RosStateProvider for RosState {
     fn from_ros(node: &r2r::Node) -> Result<RosState, r2r::Error> {
-        let mut nodes = HashMap::new();
+        let mut nodes = BTreeMap::new();
         for (name, namespace, enclave) in node.get_node_names_with_enclaves()? {
-            let mut publishers = HashMap::new();
+            let mut publishers = BTreeMap::new();
             for (topic, types) in node.get_publisher_names_and_types_by_node(&name, &namespace)? {
                 if types.len() == 1 {
                     publishers.insert(topic, types[0].clone());
                 } else {
-                    eprintln!("publisher {} has multiple types: {:?}", topic, types);
+                    eprintln!("{}", Diagnostic::multiple_types(EndpointKind::Publisher, topic, types));
                 }
             }
 
-            let mut subscribers = HashMap::new();
+            let mut subscribers = BTreeMap::new();
             for (topic, types) in node.get_subscriber_names_and_types_by_node(&name, &namespace)? {
                 if types.len() == 1 {
                     subscribers.insert(topic, types[0].clone());
                 } else {
-                    eprintln!("subscriber {} has multiple types: {:?}", topic, types);
+                    eprintln!("{}", Diagnostic::multiple_types(EndpointKind::Subscriber, topic, types));
                 }
             }
 
-            let mut clients = HashMap::new();
+            let mut clients = BTreeMap::new();
             for (name, types) in node.get_client_names_and_types_by_node(&name, &namespace)? {
                 if types.len() == 1 {
                     clients.insert(name, types[0].clone());
                 } else {
-                    eprintln!("client {} has multiple types: {:?}", name, types);
+                    eprintln!("{}", Diagnostic::multiple_types(EndpointKind::Client, name, types));
                 }
             }
 
-            let mut services = HashMap::new();
+            let mut services = BTreeMap::new();
             for (name, types) in node.get_service_names_and_types_by_node(&name, &namespace)? {
                 if types.len() == 1 {
                     services.insert(name, types[0].clone());
                 } else {
-                    eprintln!("service {} has multiple types: {:?}", name, types);
+                    eprintln!("{}", Diagnostic::multiple_types(EndpointKind::Service, name, types));
                 }
             }
 
-            nodes.insert((name, namespace), types::NodeProperties { enclave, publishers, subscribers, clients, services });
+            let properties = types::NodeProperties { enclave, publishers, subscribers, clients, services, participant: None };
+            nodes.insert((namespace, name), properties);
         }
 
-        let mut topics = HashMap::new();
+        // the participant of a node is only known through its topic endpoints,
+        // see `participant::from_gid`
+        let mut gids = BTreeMap::new();
+        let mut topics = BTreeMap::new();
         for (name, types) in node.get_topic_names_and_types()? {
             let mut publishers = vec![];
             for endpoint_info in node.get_publishers_info_by_topic(&name, false)? {
-                let r2r::TopicEndpointInfo { node_name, node_namespace, topic_type, endpoint_gid: _, qos_profile } = endpoint_info;
+                let r2r::TopicEndpointInfo { node_name, node_namespace, topic_type, endpoint_gid, qos_profile } = endpoint_info;
+                gids.entry((node_namespace.clone(), node_name.clone())).or_insert(endpoint_gid);
                 publishers.push(types::PubSubProperties { node_name, node_namespace, topic_type, qos_profile: qos_into(qos_profile) });
             }
 
             let mut subscribers = vec![];
             for endpoint_info in node.get_subscriptions_info_by_topic(&name, false)? {
-                let r2r::TopicEndpointInfo { node_name, node_namespace, topic_type, endpoint_gid: _, qos_profile } = endpoint_info;
+                let r2r::TopicEndpointInfo { node_name, node_namespace, topic_type, endpoint_gid, qos_profile } = endpoint_info;
+                gids.entry((node_namespace.clone(), node_name.clone())).or_insert(endpoint_gid);
                 subscribers.push(types::PubSubProperties { node_name, node_namespace, topic_type, qos_profile: qos_into(qos_profile) });
             }
 
+            // in a stable order, rather than the one of the middleware
+            publishers.sort();
+            subscribers.sort();
             topics.insert(name.clone(), types::TopicProperties { types, publishers, subscribers });
         }
 
-        let mut services = HashMap::new();
+        let mut services = BTreeMap::new();
         for (name, types) in node.get_service_names_and_types()? {
             services.insert(name, types::ServiceProperties { types });
         }
 
-        Ok(Self { nodes, topics, services })
+        for (key, properties) in nodes.iter_mut() {
+            properties.participant = gids.get(key).and_then(|gid| participant::from_gid(gid));
+        }
+
+        let mut state = Self { nodes, topics, services };
+        participant::name_local_host(&mut state);
+        Ok(state)
     }
 }
 
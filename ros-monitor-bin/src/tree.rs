@@ -0,0 +1,33 @@
+use std::io::Write;
+
+use crate::output::OutputFormat;
+use crate::pretty;
+
+// Prints the namespace tree once discovery has settled, from `namespace` down
+// and collapsed below `depth` levels.
+pub fn tree(node: &str, interval: u64, namespace: &str, depth: Option<usize>, format: Option<OutputFormat>) {
+    if !matches!(format, None | Some(OutputFormat::Pretty | OutputFormat::Json | OutputFormat::Yaml)) {
+        eprintln!("the namespace tree can only be written as pretty, json or yaml");
+        std::process::exit(1);
+    }
+
+    let (_, state) = crate::settled_state(node, interval);
+    let Some(mut tree) = state.namespace_tree().subtree(namespace).cloned() else {
+        eprintln!("namespace {} not found", namespace);
+        std::process::exit(1);
+    };
+    if let Some(depth) = depth {
+        tree.collapse(depth);
+    }
+
+    let mut stdout = std::io::stdout();
+    match format {
+        Some(OutputFormat::Json) => {
+            serde_json::to_writer_pretty(&mut stdout, &tree).unwrap();
+            stdout.write_all(b"\n").unwrap();
+        }
+        Some(OutputFormat::Yaml) => serde_yaml::to_writer(&mut stdout, &tree).unwrap(),
+        _ => stdout.write_all(pretty::namespace_tree(&tree, pretty::use_color()).as_bytes()).unwrap(),
+    }
+    stdout.flush().unwrap();
+}
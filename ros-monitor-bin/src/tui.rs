@@ -0,0 +1,340 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style, Stylize};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, List, ListItem, ListState, Paragraph, Wrap};
+use ratatui::{DefaultTerminal, Frame};
+use ros_monitor_lib::filter::fully_qualified_name;
+use ros_monitor_lib::state::RosState;
+use ros_monitor_lib::types::{DiscoveryEvent, PubSubProperties, QosProfile};
+
+// How long added and removed entities stay highlighted.
+const HIGHLIGHT: Duration = Duration::from_secs(3);
+const MAX_LOG_LINES: usize = 200;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Pane {
+    Nodes,
+    Topics,
+    Services,
+}
+
+impl Pane {
+    const ALL: [Pane; 3] = [Pane::Nodes, Pane::Topics, Pane::Services];
+
+    fn title(&self) -> &'static str {
+        match self {
+            Pane::Nodes => "Nodes",
+            Pane::Topics => "Topics",
+            Pane::Services => "Services",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Change {
+    Added,
+    Updated,
+    Removed,
+}
+
+struct App {
+    state: RosState,
+    focus: Pane,
+    // by name, so that the selection doesn't jump when the graph changes
+    selected: HashMap<Pane, String>,
+    changes: HashMap<(Pane, String), (Change, Instant)>,
+    log: VecDeque<(Change, Pane, String)>,
+}
+
+pub fn tui(node: &str, interval: u64) -> std::io::Result<()> {
+    let (states, states_rx) = mpsc::channel();
+    let node = node.to_owned();
+    std::thread::spawn(move || {
+        crate::discovery_loop(&node, interval, |_, state, _| {
+            let _ = states.send(state);
+        });
+    });
+
+    let mut terminal = ratatui::init();
+    let result = run(&mut terminal, states_rx);
+    ratatui::restore();
+    result
+}
+
+fn run(terminal: &mut DefaultTerminal, states: mpsc::Receiver<RosState>) -> std::io::Result<()> {
+    let mut app = App {
+        state: RosState::default(),
+        focus: Pane::Nodes,
+        selected: HashMap::new(),
+        changes: HashMap::new(),
+        log: VecDeque::new(),
+    };
+
+    loop {
+        while let Ok(state) = states.try_recv() {
+            app.update(state);
+        }
+        app.changes.retain(|_, (_, at)| at.elapsed() < HIGHLIGHT);
+
+        terminal.draw(|frame| app.draw(frame))?;
+
+        if !event::poll(Duration::from_millis(100))? {
+            continue;
+        }
+        let Event::Key(key) = event::read()? else { continue };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+            KeyCode::Tab | KeyCode::Right => app.focus = Pane::ALL[(app.focus as usize + 1) % Pane::ALL.len()],
+            KeyCode::BackTab | KeyCode::Left => app.focus = Pane::ALL[(app.focus as usize + Pane::ALL.len() - 1) % Pane::ALL.len()],
+            KeyCode::Down | KeyCode::Char('j') => app.move_selection(1),
+            KeyCode::Up | KeyCode::Char('k') => app.move_selection(-1),
+            _ => {}
+        }
+    }
+}
+
+impl App {
+    fn update(&mut self, state: RosState) {
+        let now = Instant::now();
+        for event in state.changes(&self.state) {
+            let (pane, name, change) = match event {
+                DiscoveryEvent::NodeAdded { name, namespace, .. } => (Pane::Nodes, fully_qualified_name(&namespace, &name), Change::Added),
+                DiscoveryEvent::NodeRemoved { name, namespace } => (Pane::Nodes, fully_qualified_name(&namespace, &name), Change::Removed),
+                DiscoveryEvent::TopicAdded { name, .. } => (Pane::Topics, name, Change::Added),
+                DiscoveryEvent::TopicRemoved { name } => (Pane::Topics, name, Change::Removed),
+                DiscoveryEvent::ServiceAdded { name, .. } => (Pane::Services, name, Change::Added),
+                DiscoveryEvent::ServiceRemoved { name } => (Pane::Services, name, Change::Removed),
+                DiscoveryEvent::Ping | DiscoveryEvent::Unknown => continue,
+            };
+
+            // entities that change are added again
+            let change = if change == Change::Added && self.contains(pane, &name) { Change::Updated } else { change };
+            self.changes.insert((pane, name.clone()), (change, now));
+
+            if self.log.len() == MAX_LOG_LINES {
+                self.log.pop_front();
+            }
+            self.log.push_back((change, pane, name));
+        }
+        self.state = state;
+    }
+
+    fn contains(&self, pane: Pane, name: &str) -> bool {
+        match pane {
+            Pane::Nodes => self.state.nodes.keys().any(|(namespace, node_name)| fully_qualified_name(namespace, node_name) == name),
+            Pane::Topics => self.state.topics.contains_key(name),
+            Pane::Services => self.state.services.contains_key(name),
+        }
+    }
+
+    // Current entities, plus the ones removed recently.
+    fn names(&self, pane: Pane) -> Vec<String> {
+        let mut names: Vec<String> = match pane {
+            Pane::Nodes => self.state.nodes.keys().map(|(namespace, name)| fully_qualified_name(namespace, name)).collect(),
+            Pane::Topics => self.state.topics.keys().cloned().collect(),
+            Pane::Services => self.state.services.keys().cloned().collect(),
+        };
+        for ((changed_pane, name), (change, _)) in &self.changes {
+            if *changed_pane == pane && *change == Change::Removed && !names.contains(name) {
+                names.push(name.clone());
+            }
+        }
+        names.sort();
+        names
+    }
+
+    fn selected_index(&self, pane: Pane, names: &[String]) -> Option<usize> {
+        if names.is_empty() {
+            return None;
+        }
+        let selected = self.selected.get(&pane)?;
+        // the closest entry when the selected one is gone
+        Some(names.binary_search(selected).unwrap_or_else(|i| i.min(names.len() - 1)))
+    }
+
+    fn move_selection(&mut self, offset: isize) {
+        let names = self.names(self.focus);
+        if names.is_empty() {
+            return;
+        }
+        let index = match self.selected_index(self.focus, &names) {
+            Some(index) => index.saturating_add_signed(offset).min(names.len() - 1),
+            None => 0,
+        };
+        self.selected.insert(self.focus, names[index].clone());
+    }
+
+    fn draw(&self, frame: &mut Frame) {
+        let [lists, bottom, help] = Layout::vertical([Constraint::Percentage(55), Constraint::Fill(1), Constraint::Length(1)])
+            .areas(frame.area());
+        let [details, log] = Layout::horizontal([Constraint::Percentage(65), Constraint::Fill(1)]).areas(bottom);
+
+        let panes = Layout::horizontal([Constraint::Ratio(1, 3); 3]).split(lists);
+        for (pane, area) in Pane::ALL.into_iter().zip(panes.iter()) {
+            self.draw_list(frame, pane, *area);
+        }
+        self.draw_details(frame, details);
+        self.draw_log(frame, log);
+
+        let help_line = Line::from("tab/←→ switch pane · ↑↓/jk select · q quit").dim();
+        frame.render_widget(help_line, help);
+    }
+
+    fn draw_list(&self, frame: &mut Frame, pane: Pane, area: Rect) {
+        let names = self.names(pane);
+        let items: Vec<ListItem> = names.iter()
+            .map(|name| {
+                let style = match self.changes.get(&(pane, name.clone())) {
+                    Some((Change::Added, _)) => Style::new().fg(Color::Green).add_modifier(Modifier::BOLD),
+                    Some((Change::Updated, _)) => Style::new().fg(Color::Yellow),
+                    Some((Change::Removed, _)) => Style::new().fg(Color::Red).add_modifier(Modifier::CROSSED_OUT),
+                    None => Style::new(),
+                };
+                ListItem::new(name.as_str()).style(style)
+            })
+            .collect();
+
+        let mut block = Block::bordered().title(format!(" {} ({}) ", pane.title(), names.len()));
+        if pane == self.focus {
+            block = block.border_style(Style::new().fg(Color::Cyan));
+        }
+        let list = List::new(items).block(block).highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+
+        let mut list_state = ListState::default().with_selected(self.selected_index(pane, &names).or(
+            // select the first entry until the user moves
+            if pane == self.focus && !names.is_empty() { Some(0) } else { None },
+        ));
+        frame.render_stateful_widget(list, area, &mut list_state);
+    }
+
+    fn draw_details(&self, frame: &mut Frame, area: Rect) {
+        let names = self.names(self.focus);
+        let selected = self.selected_index(self.focus, &names).or(if names.is_empty() { None } else { Some(0) });
+        let lines = match selected {
+            Some(index) => self.details(self.focus, &names[index]),
+            None => vec![],
+        };
+        let paragraph = Paragraph::new(lines).block(Block::bordered().title(" Details ")).wrap(Wrap { trim: false });
+        frame.render_widget(paragraph, area);
+    }
+
+    fn details(&self, pane: Pane, name: &str) -> Vec<Line<'static>> {
+        let mut lines = vec![Line::from(name.to_owned()).bold()];
+
+        match pane {
+            Pane::Nodes => {
+                let Some(((namespace, node_name), properties)) = self.state.nodes.iter()
+                    .find(|((node_name, namespace), _)| fully_qualified_name(namespace, node_name) == name)
+                else {
+                    lines.push(Line::from("removed").red());
+                    return lines;
+                };
+                lines.push(Line::from(format!("enclave: {}", properties.enclave)).dim());
+                if let Some(participant) = &properties.participant {
+                    let host = participant.host_label().unwrap_or_else(|| "unknown".to_owned());
+                    let pid = participant.pid.map_or_else(|| "unknown".to_owned(), |pid| pid.to_string());
+                    lines.push(Line::from(format!("host: {}, pid: {}", host, pid)).dim());
+                    lines.push(Line::from(format!("participant: {}", participant.guid_prefix)).dim());
+                }
+
+                // QoS is only known for topic endpoints, from the topic itself
+                let endpoints = [
+                    ("Publishers", &properties.publishers, Some(true)),
+                    ("Subscribers", &properties.subscribers, Some(false)),
+                    ("Clients", &properties.clients, None),
+                    ("Services", &properties.services, None),
+                ];
+                for (title, endpoints, publishers) in endpoints {
+                    lines.push(Line::default());
+                    lines.push(Line::from(format!("{} ({})", title, endpoints.len())).underlined());
+                    for (endpoint, r#type) in endpoints {
+                        lines.push(Line::from(vec![
+                            Span::raw(format!("  {} ", endpoint)),
+                            Span::raw(format!("[{}]", r#type)).cyan(),
+                        ]));
+                        let Some(publishers) = publishers else { continue };
+                        let topic = self.state.topics.get(endpoint.as_str());
+                        let topic_endpoints = topic.map(|topic| if publishers { &topic.publishers } else { &topic.subscribers });
+                        for topic_endpoint in topic_endpoints.into_iter().flatten() {
+                            if &topic_endpoint.node_name == node_name && &topic_endpoint.node_namespace == namespace {
+                                lines.push(Line::from(format!("    {}", qos_summary(&topic_endpoint.qos_profile))).dim());
+                            }
+                        }
+                    }
+                }
+            }
+            Pane::Topics => {
+                let Some(properties) = self.state.topics.get(name) else {
+                    lines.push(Line::from("removed").red());
+                    return lines;
+                };
+                lines.push(Line::from(format!("[{}]", properties.types.join(", "))).cyan());
+                for (title, endpoints) in [("Publishers", &properties.publishers), ("Subscribers", &properties.subscribers)] {
+                    lines.push(Line::default());
+                    lines.push(Line::from(format!("{} ({})", title, endpoints.len())).underlined());
+                    for endpoint in endpoints {
+                        lines.extend(endpoint_lines(endpoint));
+                    }
+                }
+            }
+            Pane::Services => {
+                let Some(properties) = self.state.services.get(name) else {
+                    lines.push(Line::from("removed").red());
+                    return lines;
+                };
+                lines.push(Line::from(format!("[{}]", properties.types.join(", "))).cyan());
+            }
+        }
+
+        lines
+    }
+
+    fn draw_log(&self, frame: &mut Frame, area: Rect) {
+        let visible = area.height.saturating_sub(2) as usize;
+        let lines: Vec<Line> = self.log.iter().rev().take(visible)
+            .map(|(change, pane, name)| {
+                let (sign, color) = match change {
+                    Change::Added => ("+", Color::Green),
+                    Change::Updated => ("~", Color::Yellow),
+                    Change::Removed => ("-", Color::Red),
+                };
+                let kind = pane.title().trim_end_matches('s').to_lowercase();
+                Line::from(vec![Span::raw(format!("{} {:<7} ", sign, kind)).fg(color), Span::raw(name.clone())])
+            })
+            .collect();
+        let paragraph = Paragraph::new(lines).block(Block::bordered().title(" Events "));
+        frame.render_widget(paragraph, area);
+    }
+}
+
+fn endpoint_lines(endpoint: &PubSubProperties) -> [Line<'static>; 2] {
+    [
+        Line::from(vec![
+            Span::raw(format!("  {} ", fully_qualified_name(&endpoint.node_namespace, &endpoint.node_name))),
+            Span::raw(format!("[{}]", endpoint.topic_type)).cyan(),
+        ]),
+        Line::from(format!("    {}", qos_summary(&endpoint.qos_profile))).dim(),
+    ]
+}
+
+fn qos_summary(qos: &QosProfile) -> String {
+    let mut summary = format!("{:?} {:?} {:?}", qos.reliability, qos.durability, qos.history);
+    if matches!(qos.history, ros_monitor_lib::types::HistoryPolicy::KeepLast) {
+        summary.push_str(&format!("({})", qos.depth));
+    }
+    summary.push_str(&format!(" {:?}", qos.liveliness));
+    for (name, duration) in [("deadline", qos.deadline), ("lifespan", qos.lifespan), ("lease", qos.liveliness_lease_duration)] {
+        if !duration.is_zero() {
+            summary.push_str(&format!(" {}={:?}", name, duration));
+        }
+    }
+    summary
+}
@@ -0,0 +1,61 @@
+use std::io::Write;
+
+use ros_monitor_lib::filter::EventFilter;
+use ros_monitor_lib::protocol::Hello;
+use ros_monitor_lib::state::RosState;
+use ros_monitor_lib::types::{DiscoveryEvent, DiscoveryEventWrapper};
+
+use crate::output::{Encoder, OutputFormat};
+use crate::pretty;
+
+// Prints the changes to the entities matching `filter`, as timestamped lines,
+// or as events in `format` when given.
+pub fn watch(node: &str, interval: u64, filter: EventFilter, format: Option<OutputFormat>) {
+    // the entities matching `filter`, which is also what `-f table` shows
+    let mut state = RosState::default();
+    let mut seq = 0;
+    let mut stdout = std::io::stdout();
+    let mut encoder = format.map(Encoder::new);
+    let color = pretty::use_color();
+
+    if let Some(encoder) = &mut encoder {
+        stdout.write_all(encoder.hello(&Hello::new(env!("CARGO_PKG_VERSION")))).unwrap();
+        stdout.flush().unwrap();
+    }
+
+    crate::discovery_loop(node, interval, |ts, new_state, _| {
+        let events: Vec<_> = new_state.changes(&state).into_iter().filter(|event| filter.matches(event)).collect();
+        if events.is_empty() {
+            return;
+        }
+
+        for event in events {
+            match &mut encoder {
+                Some(encoder) => {
+                    seq += 1;
+                    stdout.write_all(encoder.event(&DiscoveryEventWrapper { ts, seq, event: event.clone() })).unwrap();
+                }
+                None => {
+                    if let Some(line) = pretty::event_line(&event, is_update(&state, &event), color) {
+                        write!(stdout, "{} {}", pretty::timestamp(ts, color), line).unwrap();
+                    }
+                }
+            }
+            state.update(event);
+        }
+        if let Some(encoder) = &mut encoder {
+            stdout.write_all(encoder.table(&state)).unwrap();
+        }
+        stdout.flush().unwrap();
+    });
+}
+
+// Whether `event` adds an entity that's already in `state`.
+pub fn is_update(state: &RosState, event: &DiscoveryEvent) -> bool {
+    match event {
+        DiscoveryEvent::NodeAdded { name, namespace, .. } => state.nodes.contains_key(&(namespace.clone(), name.clone())),
+        DiscoveryEvent::TopicAdded { name, .. } => state.topics.contains_key(name),
+        DiscoveryEvent::ServiceAdded { name, .. } => state.services.contains_key(name),
+        _ => false,
+    }
+}
@@ -0,0 +1,117 @@
+use std::ffi::OsString;
+use std::sync::mpsc;
+use std::time::Duration;
+
+use futures::StreamExt;
+
+use crate::{state, types, RosMonitor, RosMonitorConfig};
+
+pub use std::sync::mpsc::{RecvTimeoutError, TryRecvError};
+
+// Synchronous facade over `RosMonitor`, usable outside of a Tokio runtime.
+// It runs its own single-threaded runtime on a background thread, and buffers
+// events until they are consumed.
+pub struct BlockingRosMonitor {
+    monitor: RosMonitor,
+    events: mpsc::Receiver<types::DiscoveryEvent>,
+    stop: Option<tokio::sync::oneshot::Sender<()>>,
+    thread: Option<std::thread::JoinHandle<()>>,
+}
+
+impl BlockingRosMonitor {
+    pub fn new(command: impl Into<OsString>) -> std::io::Result<Self> {
+        Self::with_config(command, RosMonitorConfig::default())
+    }
+
+    pub fn with_config(command: impl Into<OsString>, config: RosMonitorConfig) -> std::io::Result<Self> {
+        let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build()?;
+        let command = command.into();
+        let (monitor_tx, monitor_rx) = mpsc::sync_channel(1);
+        let (events_tx, events) = mpsc::channel();
+        let (stop, stop_rx) = tokio::sync::oneshot::channel();
+
+        let thread = std::thread::Builder::new()
+            .name("ros-monitor".into())
+            .spawn(move || {
+                runtime.block_on(async move {
+                    let monitor = RosMonitor::with_config(command, config);
+                    let _ = monitor_tx.send(monitor.clone());
+
+                    tokio::select! {
+                        _ = forward_events(&monitor, events_tx) => {}
+                        _ = stop_rx => {}
+                    }
+
+                    monitor.shutdown().await;
+                });
+            })?;
+
+        let monitor = monitor_rx.recv().map_err(|_| std::io::Error::other("ros-monitor thread exited"))?;
+
+        Ok(Self {
+            monitor,
+            events,
+            stop: Some(stop),
+            thread: Some(thread),
+        })
+    }
+
+    pub fn monitor(&self) -> &RosMonitor {
+        &self.monitor
+    }
+
+    pub fn snapshot(&self) -> state::RosState {
+        self.monitor.state()
+    }
+
+    // Blocks until the next event, returns `None` once the monitor has stopped.
+    pub fn recv(&self) -> Option<types::DiscoveryEvent> {
+        self.events.recv().ok()
+    }
+
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<types::DiscoveryEvent, RecvTimeoutError> {
+        self.events.recv_timeout(timeout)
+    }
+
+    pub fn try_recv(&self) -> Result<types::DiscoveryEvent, TryRecvError> {
+        self.events.try_recv()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = types::DiscoveryEvent> + '_ {
+        self.events.iter()
+    }
+
+    pub fn try_iter(&self) -> impl Iterator<Item = types::DiscoveryEvent> + '_ {
+        self.events.try_iter()
+    }
+
+    pub fn shutdown(mut self) {
+        self.stop_and_join();
+    }
+
+    fn stop_and_join(&mut self) {
+        if let Some(stop) = self.stop.take() {
+            let _ = stop.send(());
+        }
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for BlockingRosMonitor {
+    fn drop(&mut self) {
+        self.stop_and_join();
+    }
+}
+
+async fn forward_events(monitor: &RosMonitor, events: mpsc::Sender<types::DiscoveryEvent>) {
+    let stream = monitor.subscribe_filtered(Default::default());
+    let mut stream = std::pin::pin!(stream);
+
+    while let Some(event) = stream.next().await {
+        if events.send(event).is_err() {
+            return;
+        }
+    }
+}
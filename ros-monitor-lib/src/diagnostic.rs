@@ -0,0 +1,190 @@
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Diagnostic {
+    pub level: DiagnosticLevel,
+    #[serde(flatten)]
+    pub kind: DiagnosticKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiagnosticLevel {
+    Debug,
+    Info,
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+#[serde(rename_all = "snake_case")]
+pub enum DiagnosticKind {
+    MultipleTypes {
+        endpoint: EndpointKind,
+        name: String,
+        types: Vec<String>,
+    },
+    Message {
+        message: String,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EndpointKind {
+    Publisher,
+    Subscriber,
+    Client,
+    Service,
+}
+
+impl Diagnostic {
+    pub fn multiple_types(endpoint: EndpointKind, name: impl Into<String>, types: Vec<String>) -> Self {
+        Self {
+            level: DiagnosticLevel::Warning,
+            kind: DiagnosticKind::MultipleTypes { endpoint, name: name.into(), types },
+        }
+    }
+
+    // Parses a line written to stderr by the binary, either one of our own
+    // diagnostics (see `Display`) or an rcl/rmw log line like `[WARN] [...] [rcl]: ...`.
+    pub fn parse(line: &str) -> Self {
+        if let Some((level, rest)) = line.split_once(": ") {
+            if let Some(level) = DiagnosticLevel::parse(level) {
+                if let Some(kind) = DiagnosticKind::parse_multiple_types(rest) {
+                    return Self { level, kind };
+                }
+                return Self { level, kind: DiagnosticKind::Message { message: rest.to_owned() } };
+            }
+        }
+
+        if let Some(rest) = line.strip_prefix('[') {
+            if let Some((level, _)) = rest.split_once(']') {
+                if let Some(level) = DiagnosticLevel::parse(level) {
+                    return Self { level, kind: DiagnosticKind::Message { message: line.to_owned() } };
+                }
+            }
+        }
+
+        Self {
+            level: DiagnosticLevel::Info,
+            kind: DiagnosticKind::Message { message: line.to_owned() },
+        }
+    }
+
+    pub fn log(&self) {
+        let level = match self.level {
+            DiagnosticLevel::Debug => log::Level::Debug,
+            DiagnosticLevel::Info => log::Level::Info,
+            DiagnosticLevel::Warning => log::Level::Warn,
+            DiagnosticLevel::Error => log::Level::Error,
+        };
+        log::log!(level, "intrepid-ros-monitor: {}", self.kind);
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.level, self.kind)
+    }
+}
+
+impl DiagnosticLevel {
+    fn parse(level: &str) -> Option<Self> {
+        match level.trim() {
+            "debug" | "DEBUG" => Some(Self::Debug),
+            "info" | "INFO" => Some(Self::Info),
+            "warning" | "WARN" => Some(Self::Warning),
+            "error" | "ERROR" | "FATAL" => Some(Self::Error),
+            _ => None,
+        }
+    }
+}
+
+impl Display for DiagnosticLevel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Debug => "debug",
+            Self::Info => "info",
+            Self::Warning => "warning",
+            Self::Error => "error",
+        })
+    }
+}
+
+impl DiagnosticKind {
+    fn parse_multiple_types(line: &str) -> Option<Self> {
+        let (endpoint, rest) = line.split_once(' ')?;
+        let endpoint = EndpointKind::parse(endpoint)?;
+        let (name, types) = rest.split_once(" has multiple types: ")?;
+        let types = types.split(", ").map(str::to_owned).collect();
+        Some(Self::MultipleTypes { endpoint, name: name.to_owned(), types })
+    }
+}
+
+impl Display for DiagnosticKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::MultipleTypes { endpoint, name, types } => {
+                write!(f, "{} {} has multiple types: {}", endpoint, name, types.join(", "))
+            }
+            Self::Message { message } => f.write_str(message),
+        }
+    }
+}
+
+impl EndpointKind {
+    fn parse(endpoint: &str) -> Option<Self> {
+        match endpoint {
+            "publisher" => Some(Self::Publisher),
+            "subscriber" => Some(Self::Subscriber),
+            "client" => Some(Self::Client),
+            "service" => Some(Self::Service),
+            _ => None,
+        }
+    }
+}
+
+impl Display for EndpointKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Publisher => "publisher",
+            Self::Subscriber => "subscriber",
+            Self::Client => "client",
+            Self::Service => "service",
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn multiple_types_round_trip() {
+        let types = vec!["std_msgs/msg/String".to_owned(), "std_msgs/msg/Header".to_owned()];
+        let diagnostic = Diagnostic::multiple_types(EndpointKind::Subscriber, "/chatter", types);
+        assert_eq!(diagnostic.to_string(), "warning: subscriber /chatter has multiple types: std_msgs/msg/String, std_msgs/msg/Header");
+        assert_eq!(Diagnostic::parse(&diagnostic.to_string()), diagnostic);
+    }
+
+    #[test]
+    fn rcl_log_line() {
+        let line = "[WARN] [1700000000.123456789] [rcl]: Publisher already registered for provided node name";
+        let diagnostic = Diagnostic::parse(line);
+        assert_eq!(diagnostic.level, DiagnosticLevel::Warning);
+        assert_eq!(diagnostic.kind, DiagnosticKind::Message { message: line.to_owned() });
+    }
+
+    #[test]
+    fn plain_line() {
+        for line in ["thread 'main' panicked at src/main.rs:1:1", "note: run with `RUST_BACKTRACE=1`"] {
+            let diagnostic = Diagnostic::parse(line);
+            assert_eq!(diagnostic.level, DiagnosticLevel::Info);
+            assert_eq!(diagnostic.kind, DiagnosticKind::Message { message: line.to_owned() });
+        }
+    }
+}
@@ -0,0 +1,167 @@
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+
+use futures::{Stream, StreamExt};
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::state::RosState;
+use crate::types::DomainEventWrapper;
+use crate::{RosMonitor, RosMonitorConfig};
+
+// Monitors several ROS domains at once, with one process per domain since a
+// node only discovers the graph of its own domain.
+#[derive(Clone, Default)]
+pub struct MultiDomainMonitor {
+    monitors: BTreeMap<u32, RosMonitor>,
+}
+
+impl MultiDomainMonitor {
+    pub fn new(command: impl Into<OsString>, domain_ids: impl IntoIterator<Item = u32>) -> Self {
+        Self::with_config(command, domain_ids, RosMonitorConfig::default())
+    }
+
+    // `config.domain_id` is set for each domain, a `daemon_socket` or `remote`
+    // daemon would be shared by all of them.
+    pub fn with_config(command: impl Into<OsString>, domain_ids: impl IntoIterator<Item = u32>, config: RosMonitorConfig) -> Self {
+        let command = command.into();
+        let monitors = domain_ids.into_iter()
+            .map(|domain_id| {
+                let config = RosMonitorConfig { domain_id: Some(domain_id), ..config.clone() };
+                (domain_id, RosMonitor::with_config(command.clone(), config))
+            })
+            .collect();
+        Self { monitors }
+    }
+
+    // Combines monitors created otherwise, e.g. connected to a daemon per domain.
+    pub fn from_monitors(monitors: BTreeMap<u32, RosMonitor>) -> Self {
+        Self { monitors }
+    }
+
+    pub fn monitors(&self) -> &BTreeMap<u32, RosMonitor> {
+        &self.monitors
+    }
+
+    pub fn monitor(&self, domain_id: u32) -> Option<&RosMonitor> {
+        self.monitors.get(&domain_id)
+    }
+
+    pub fn state(&self) -> BTreeMap<u32, RosState> {
+        self.monitors.iter().map(|(&domain_id, monitor)| (domain_id, monitor.state())).collect()
+    }
+
+    // The events of all domains as they come, each domain starting with its
+    // current state, see `RosMonitor::subscribe_since`. A domain that falls
+    // behind yields `Lagged`, then the events it missed from the history, or
+    // the changes bringing it up to date when the history doesn't go back far
+    // enough. Ends when the monitors of all domains stop.
+    pub fn subscribe(&self) -> Result<impl Stream<Item = Result<DomainEventWrapper, RecvError>>, RecvError> {
+        let mut streams = vec![];
+        for (&domain_id, monitor) in &self.monitors {
+            streams.push(domain_events(domain_id, monitor.clone())?.boxed());
+        }
+        Ok(futures::stream::select_all(streams))
+    }
+
+    pub async fn shutdown(&self) {
+        futures::future::join_all(self.monitors.values().map(RosMonitor::shutdown)).await;
+    }
+}
+
+fn domain_events(domain_id: u32, monitor: RosMonitor) -> Result<impl Stream<Item = Result<DomainEventWrapper, RecvError>>, RecvError> {
+    let (_, stream) = monitor.subscribe_since(None)?;
+
+    Ok(async_stream::stream! {
+        let mut stream = stream.boxed();
+        let mut since = None;
+        // what was yielded so far, to resume from when the history falls short
+        let mut known = RosState::default();
+
+        loop {
+            match stream.next().await {
+                Some(Ok(event)) => {
+                    since = Some(event.seq);
+                    known.update(event.event.clone());
+                    yield Ok(DomainEventWrapper { domain_id, event });
+                }
+                None | Some(Err(RecvError::Closed)) => break,
+                Some(Err(RecvError::Lagged(count))) => {
+                    yield Err(RecvError::Lagged(count));
+                    let Ok((_, resumed)) = monitor.resume(since, &known) else { break };
+                    stream = resumed.boxed();
+                }
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::TopicProperties;
+
+    fn topics(names: &[&str]) -> RosState {
+        let mut state = RosState::default();
+        for name in names {
+            state.topics.insert(name.to_string(), TopicProperties { types: vec![], publishers: vec![], subscribers: vec![] });
+        }
+        state
+    }
+
+    #[tokio::test]
+    async fn lagging_domain_keeps_going() {
+        let config = RosMonitorConfig { channel_capacity: 1, ..Default::default() };
+        let (lagging, feed) = RosMonitor::manual(config);
+        let (other, other_feed) = RosMonitor::manual(Default::default());
+        let monitor = MultiDomainMonitor::from_monitors(BTreeMap::from([(1, lagging), (2, other)]));
+        let events = monitor.subscribe().unwrap();
+        let mut events = std::pin::pin!(events);
+
+        feed.update(100, topics(&["/a", "/b", "/c"]));
+        assert!(matches!(events.next().await, Some(Err(RecvError::Lagged(_)))));
+        for _ in 0..3 {
+            assert!(matches!(events.next().await, Some(Ok(DomainEventWrapper { domain_id: 1, .. }))));
+        }
+
+        feed.update(200, topics(&["/a", "/b", "/c", "/d"]));
+        let event = events.next().await.unwrap().unwrap();
+        assert_eq!((event.domain_id, event.event.seq), (1, 4));
+
+        // the other domain still comes through once the first one stopped
+        drop(feed);
+        other_feed.update(300, topics(&["/e"]));
+        let other_event = loop {
+            match events.next().await {
+                Some(Ok(event)) if event.domain_id == 2 => break Some(event),
+                Some(_) => {}
+                None => break None,
+            }
+        };
+        assert!(other_event.is_some());
+    }
+
+    #[tokio::test]
+    async fn lagging_beyond_the_history_catches_up_with_removals() {
+        let config = RosMonitorConfig { channel_capacity: 1, history_size: 1, ..Default::default() };
+        let (lagging, feed) = RosMonitor::manual(config);
+        let monitor = MultiDomainMonitor::from_monitors(BTreeMap::from([(1, lagging.clone())]));
+        let events = monitor.subscribe().unwrap();
+        let mut events = std::pin::pin!(events);
+
+        feed.update(100, topics(&["/a"]));
+        let mut state = RosState::default();
+        state.update(events.next().await.unwrap().unwrap().event.event);
+
+        // removes `/a` and adds three topics, more than the channel and the history hold
+        feed.update(200, topics(&["/b", "/c", "/d"]));
+        assert!(matches!(events.next().await, Some(Err(RecvError::Lagged(_)))));
+        let mut kinds = vec![];
+        for _ in 0..4 {
+            let event = events.next().await.unwrap().unwrap().event.event;
+            kinds.push(event.kind());
+            state.update(event);
+        }
+        assert_eq!(kinds, ["topic_removed", "topic_added", "topic_added", "topic_added"]);
+        assert_eq!(state, lagging.state());
+    }
+}
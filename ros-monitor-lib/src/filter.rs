@@ -0,0 +1,162 @@
+use crate::types::DiscoveryEvent;
+
+pub use crate::name::fully_qualified_name;
+
+// Glob over ROS names: `*` matches within one name segment, `**` matches
+// across segments, `?` matches a single character other than `/`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pattern(String);
+
+impl Pattern {
+    pub fn new(pattern: impl Into<String>) -> Self {
+        Self(pattern.into())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    pub fn matches(&self, name: &str) -> bool {
+        glob_match(self.0.as_bytes(), name.as_bytes())
+    }
+}
+
+fn glob_match(pattern: &[u8], name: &[u8]) -> bool {
+    match pattern {
+        [] => name.is_empty(),
+        [b'*', b'*', rest @ ..] => (0..=name.len()).any(|i| glob_match(rest, &name[i..])),
+        [b'*', rest @ ..] => {
+            let segment = name.iter().position(|&c| c == b'/').unwrap_or(name.len());
+            (0..=segment).any(|i| glob_match(rest, &name[i..]))
+        }
+        [b'?', rest @ ..] => matches!(name, [c, name @ ..] if *c != b'/' && glob_match(rest, name)),
+        [p, rest @ ..] => matches!(name, [c, name @ ..] if c == p && glob_match(rest, name)),
+    }
+}
+
+// Selects events by entity kind and name. An empty filter lets everything
+// through, otherwise only kinds with at least one pattern are kept. Nodes are
+// matched by their fully qualified name (`/namespace/name`).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EventFilter {
+    pub nodes: Option<Vec<Pattern>>,
+    pub topics: Option<Vec<Pattern>>,
+    pub services: Option<Vec<Pattern>>,
+}
+
+impl EventFilter {
+    // Everything in `namespace` and below.
+    pub fn namespace(namespace: &str) -> Self {
+        let patterns = Some(vec![Pattern::new(format!("{}/**", namespace.trim_end_matches('/')))]);
+        Self { nodes: patterns.clone(), topics: patterns.clone(), services: patterns }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_none() && self.topics.is_none() && self.services.is_none()
+    }
+
+    pub fn matches(&self, event: &DiscoveryEvent) -> bool {
+        if self.is_empty() {
+            return true;
+        }
+
+        let (patterns, name) = match event {
+            DiscoveryEvent::NodeAdded { name, namespace, .. } | DiscoveryEvent::NodeRemoved { name, namespace } => {
+                (&self.nodes, fully_qualified_name(namespace, name))
+            }
+            DiscoveryEvent::TopicAdded { name, .. } | DiscoveryEvent::TopicRemoved { name } => (&self.topics, name.clone()),
+            DiscoveryEvent::ServiceAdded { name, .. } | DiscoveryEvent::ServiceRemoved { name } => (&self.services, name.clone()),
+            DiscoveryEvent::Ping | DiscoveryEvent::Unknown => return false,
+        };
+
+        patterns.as_ref().is_some_and(|patterns| patterns.iter().any(|pattern| pattern.matches(&name)))
+    }
+}
+
+// Parses a comma separated list of patterns, e.g. `/robot*/odom,/tf`.
+pub fn parse_patterns(patterns: &str) -> Vec<Pattern> {
+    patterns.split(',').map(str::trim).filter(|s| !s.is_empty()).map(Pattern::new).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(pattern: &str, name: &str) -> bool {
+        Pattern::new(pattern).matches(name)
+    }
+
+    #[test]
+    fn star_stays_within_a_segment() {
+        assert!(matches("/robot*/odom", "/robot1/odom"));
+        assert!(matches("/robot*/odom", "/robot/odom"));
+        assert!(!matches("/robot*/odom", "/robot1/base/odom"));
+        assert!(matches("/*", "/odom"));
+        assert!(!matches("/*", "/robot1/odom"));
+        assert!(matches("/robot1/*", "/robot1/"));
+    }
+
+    #[test]
+    fn double_star_crosses_segments() {
+        assert!(matches("/ns/**", "/ns/odom"));
+        assert!(matches("/ns/**", "/ns/a/b/odom"));
+        assert!(!matches("/ns/**", "/ns"));
+        assert!(!matches("/ns/**", "/nsx"));
+        assert!(!matches("/ns/**", "/nsx/odom"));
+        assert!(matches("/**/odom", "/robot1/base/odom"));
+        assert!(matches("**", "/anything/at/all"));
+    }
+
+    #[test]
+    fn question_mark_is_one_character() {
+        assert!(matches("/robot?/odom", "/robot1/odom"));
+        assert!(!matches("/robot?/odom", "/robot/odom"));
+        assert!(!matches("/robot?/odom", "/robot12/odom"));
+        assert!(!matches("/a?b", "/a/b"));
+    }
+
+    #[test]
+    fn literal() {
+        assert!(matches("/odom", "/odom"));
+        assert!(!matches("/odom", "/odom2"));
+        assert!(!matches("/odom", "/robot1/odom"));
+        assert!(matches("", ""));
+    }
+
+    #[test]
+    fn patterns() {
+        assert_eq!(parse_patterns(" /tf, /robot*/odom ,,"), [Pattern::new("/tf"), Pattern::new("/robot*/odom")]);
+        assert!(parse_patterns("").is_empty());
+    }
+
+    #[test]
+    fn event_filter() {
+        let node = |namespace: &str, name: &str| DiscoveryEvent::NodeRemoved { name: name.to_owned(), namespace: namespace.to_owned() };
+        let topic = |name: &str| DiscoveryEvent::TopicRemoved { name: name.to_owned() };
+        let service = |name: &str| DiscoveryEvent::ServiceRemoved { name: name.to_owned() };
+
+        let everything = EventFilter::default();
+        assert!(everything.matches(&topic("/odom")) && everything.matches(&DiscoveryEvent::Ping));
+
+        let filter = EventFilter { nodes: Some(parse_patterns("/nav2/*")), topics: Some(parse_patterns("/odom")), services: None };
+        assert!(filter.matches(&node("/nav2", "planner")));
+        assert!(!filter.matches(&node("/", "planner")));
+        assert!(filter.matches(&topic("/odom")));
+        assert!(!filter.matches(&topic("/tf")));
+        assert!(!filter.matches(&service("/odom")));
+        assert!(!filter.matches(&DiscoveryEvent::Ping));
+    }
+
+    #[test]
+    fn namespace_filter() {
+        let filter = EventFilter::namespace("/robot1/");
+        assert!(filter.matches(&DiscoveryEvent::NodeRemoved { name: "talker".to_owned(), namespace: "/robot1".to_owned() }));
+        assert!(filter.matches(&DiscoveryEvent::TopicRemoved { name: "/robot1/base/odom".to_owned() }));
+        assert!(!filter.matches(&DiscoveryEvent::TopicRemoved { name: "/robot10/odom".to_owned() }));
+        assert!(!filter.matches(&DiscoveryEvent::NodeRemoved { name: "robot1".to_owned(), namespace: "/".to_owned() }));
+
+        let root = EventFilter::namespace("/");
+        assert!(root.matches(&DiscoveryEvent::NodeRemoved { name: "talker".to_owned(), namespace: "/".to_owned() }));
+        assert!(root.matches(&DiscoveryEvent::ServiceRemoved { name: "/a/b".to_owned() }));
+    }
+}
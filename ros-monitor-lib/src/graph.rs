@@ -0,0 +1,232 @@
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+
+use crate::filter::fully_qualified_name;
+use crate::state::RosState;
+use crate::types::NodeProperties;
+
+// Queries over the graph as seen by the nodes. Nodes are referred to by their
+// fully qualified name (`/namespace/name`), and data flows from the nodes
+// publishing a topic to the nodes subscribing to it. Results are sorted.
+impl RosState {
+    pub fn node(&self, node: &str) -> Option<&NodeProperties> {
+        self.nodes.iter()
+            .find(|((namespace, name), _)| fully_qualified_name(namespace, name) == node)
+            .map(|(_, properties)| properties)
+    }
+
+    pub fn publishers_of(&self, topic: &str) -> Vec<String> {
+        self.nodes_with(|properties| properties.publishers.contains_key(topic))
+    }
+
+    pub fn subscribers_of(&self, topic: &str) -> Vec<String> {
+        self.nodes_with(|properties| properties.subscribers.contains_key(topic))
+    }
+
+    // The topics `node` publishes or subscribes to.
+    pub fn topics_of(&self, node: &str) -> Vec<String> {
+        let Some(properties) = self.node(node) else { return vec![] };
+        let topics: BTreeSet<_> = properties.publishers.keys().chain(properties.subscribers.keys()).cloned().collect();
+        topics.into_iter().collect()
+    }
+
+    // The nodes exchanging data with `node` over a topic, in either direction.
+    pub fn neighbours(&self, node: &str) -> Vec<String> {
+        let mut neighbours: BTreeSet<_> = self.flows(true).remove(node).unwrap_or_default().into_keys().collect();
+        neighbours.extend(self.flows(false).remove(node).unwrap_or_default().into_keys());
+        neighbours.into_iter().collect()
+    }
+
+    // The nodes receiving data from `node`, directly or through at most `depth` hops.
+    pub fn downstream(&self, node: &str, depth: usize) -> Vec<String> {
+        reachable(&self.flows(true), node, depth)
+    }
+
+    // The nodes sending data to `node`, directly or through at most `depth` hops.
+    pub fn upstream(&self, node: &str, depth: usize) -> Vec<String> {
+        reachable(&self.flows(false), node, depth)
+    }
+
+    // The shortest way data flows from node `from` to node `to`, as the nodes
+    // and the topics in between, e.g. `[from, topic, node, topic, to]`.
+    pub fn path(&self, from: &str, to: &str) -> Option<Vec<String>> {
+        let flows = self.flows(true);
+        let mut previous: BTreeMap<&str, (&str, &str)> = BTreeMap::new();
+        let mut queue = VecDeque::from([from]);
+
+        while let Some(node) = queue.pop_front() {
+            if node == to {
+                let mut path = vec![to.to_owned()];
+                let mut node = to;
+                while let Some((previous_node, topic)) = previous.get(node) {
+                    path.extend([topic.to_string(), previous_node.to_string()]);
+                    node = previous_node;
+                }
+                path.reverse();
+                return Some(path);
+            }
+            for (next, topic) in flows.get(node).into_iter().flatten() {
+                if next != from && !previous.contains_key(next.as_str()) {
+                    previous.insert(next, (node, topic));
+                    queue.push_back(next);
+                }
+            }
+        }
+        None
+    }
+
+    // The nodes by the host they run on, see `ParticipantInfo::host_label`,
+    // with `None` for the nodes whose host is unknown.
+    pub fn nodes_by_host(&self) -> BTreeMap<Option<String>, Vec<String>> {
+        let mut hosts: BTreeMap<_, Vec<_>> = BTreeMap::new();
+        for ((namespace, name), properties) in &self.nodes {
+            let host = properties.participant.as_ref().and_then(|participant| participant.host_label());
+            hosts.entry(host).or_default().push(fully_qualified_name(namespace, name));
+        }
+        for nodes in hosts.values_mut() {
+            nodes.sort();
+        }
+        hosts
+    }
+
+    fn nodes_with(&self, predicate: impl Fn(&NodeProperties) -> bool) -> Vec<String> {
+        let nodes: BTreeSet<_> = self.nodes.iter()
+            .filter(|(_, properties)| predicate(properties))
+            .map(|((namespace, name), _)| fully_qualified_name(namespace, name))
+            .collect();
+        nodes.into_iter().collect()
+    }
+
+    // node -> next node -> first topic between them, following the data
+    // downstream, or upstream against it.
+    fn flows(&self, downstream: bool) -> BTreeMap<String, BTreeMap<String, String>> {
+        let mut publishers: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+        let mut subscribers: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+        for ((namespace, name), properties) in &self.nodes {
+            let node = fully_qualified_name(namespace, name);
+            for topic in properties.publishers.keys() {
+                publishers.entry(topic).or_default().push(node.clone());
+            }
+            for topic in properties.subscribers.keys() {
+                subscribers.entry(topic).or_default().push(node.clone());
+            }
+        }
+
+        let mut flows: BTreeMap<String, BTreeMap<String, String>> = BTreeMap::new();
+        for (topic, publishers) in &publishers {
+            for publisher in publishers {
+                for subscriber in subscribers.get(topic).into_iter().flatten() {
+                    let (node, next) = if downstream { (publisher, subscriber) } else { (subscriber, publisher) };
+                    if node != next {
+                        flows.entry(node.clone()).or_default().entry(next.clone()).or_insert_with(|| topic.to_string());
+                    }
+                }
+            }
+        }
+        flows
+    }
+}
+
+fn reachable(flows: &BTreeMap<String, BTreeMap<String, String>>, node: &str, depth: usize) -> Vec<String> {
+    let mut found = BTreeSet::new();
+    let mut frontier = vec![node];
+    for _ in 0..depth {
+        let mut next = vec![];
+        for node in frontier {
+            for reached in flows.get(node).into_iter().flat_map(BTreeMap::keys) {
+                if found.insert(reached.as_str()) {
+                    next.push(reached.as_str());
+                }
+            }
+        }
+        frontier = next;
+    }
+    found.remove(node);
+    found.into_iter().map(str::to_owned).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::ParticipantInfo;
+
+    //   /camera --/image--> /detector --/objects--> /planner <--/cmd_vel,/odom--> /robot1/driver
+    //      |                    |
+    //      +--/image--> /logger <--/objects--+
+    fn state() -> RosState {
+        let mut state = RosState::default();
+        let mut add = |node: &str, publishers: &[&str], subscribers: &[&str], host_id: Option<u16>| {
+            let (namespace, name) = node.rsplit_once('/').unwrap();
+            let topics = |topics: &[&str]| topics.iter().map(|topic| (topic.to_string(), "std_msgs/msg/String".to_owned())).collect();
+            let properties = NodeProperties {
+                enclave: "/".to_owned(),
+                publishers: topics(publishers),
+                subscribers: topics(subscribers),
+                clients: BTreeMap::new(),
+                services: BTreeMap::new(),
+                participant: host_id.map(|host_id| ParticipantInfo { host_id: Some(host_id), ..Default::default() }),
+            };
+            let namespace = if namespace.is_empty() { "/" } else { namespace };
+            state.nodes.insert((namespace.to_owned(), name.to_owned()), properties);
+        };
+        add("/camera", &["/image"], &[], Some(1));
+        add("/detector", &["/objects"], &["/image"], Some(2));
+        add("/logger", &[], &["/image", "/objects"], Some(2));
+        add("/planner", &["/cmd_vel"], &["/objects", "/odom"], None);
+        add("/robot1/driver", &["/odom"], &["/cmd_vel"], Some(1));
+        state
+    }
+
+    #[test]
+    fn endpoints() {
+        let state = state();
+        assert_eq!(state.publishers_of("/image"), ["/camera"]);
+        assert_eq!(state.subscribers_of("/image"), ["/detector", "/logger"]);
+        assert!(state.publishers_of("/unknown").is_empty());
+        assert_eq!(state.topics_of("/detector"), ["/image", "/objects"]);
+        assert!(state.topics_of("/unknown").is_empty());
+        assert!(state.node("/robot1/driver").is_some());
+        assert!(state.node("/driver").is_none());
+    }
+
+    #[test]
+    fn neighbours() {
+        let state = state();
+        assert_eq!(state.neighbours("/detector"), ["/camera", "/logger", "/planner"]);
+        assert_eq!(state.neighbours("/planner"), ["/detector", "/robot1/driver"]);
+        assert!(state.neighbours("/unknown").is_empty());
+    }
+
+    #[test]
+    fn reachability() {
+        let state = state();
+        assert!(state.downstream("/camera", 0).is_empty());
+        assert_eq!(state.downstream("/camera", 1), ["/detector", "/logger"]);
+        assert_eq!(state.downstream("/camera", 2), ["/detector", "/logger", "/planner"]);
+        // the cycle between the planner and the driver doesn't bring the node itself back
+        assert_eq!(state.downstream("/planner", 10), ["/robot1/driver"]);
+        assert_eq!(state.upstream("/planner", 1), ["/detector", "/robot1/driver"]);
+        assert_eq!(state.upstream("/logger", 10), ["/camera", "/detector"]);
+        assert_eq!(state.upstream("/robot1/driver", 10), ["/camera", "/detector", "/planner"]);
+        assert!(state.upstream("/camera", 10).is_empty());
+    }
+
+    #[test]
+    fn shortest_path() {
+        let state = state();
+        assert_eq!(
+            state.path("/camera", "/robot1/driver").unwrap(),
+            ["/camera", "/image", "/detector", "/objects", "/planner", "/cmd_vel", "/robot1/driver"],
+        );
+        assert_eq!(state.path("/robot1/driver", "/planner").unwrap(), ["/robot1/driver", "/odom", "/planner"]);
+        assert_eq!(state.path("/camera", "/camera").unwrap(), ["/camera"]);
+        assert_eq!(state.path("/logger", "/camera"), None);
+    }
+
+    #[test]
+    fn hosts() {
+        let hosts = state().nodes_by_host();
+        assert_eq!(hosts[&Some("host-0001".to_owned())], ["/camera", "/robot1/driver"]);
+        assert_eq!(hosts[&Some("host-0002".to_owned())], ["/detector", "/logger"]);
+        assert_eq!(hosts[&None], ["/planner"]);
+    }
+}
@@ -1,17 +1,45 @@
+use std::collections::{BTreeMap, VecDeque};
 use std::ffi::OsString;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 use thiserror::Error;
 use tokio::sync::broadcast::error::RecvError;
 
+pub mod blocking;
+pub mod diagnostic;
+pub mod domains;
+pub mod filter;
+mod graph;
+pub mod metrics;
+pub mod name;
+pub mod namespace;
+pub mod participant;
+pub mod protocol;
+pub mod remote;
+#[cfg(feature = "server")]
+pub mod server;
+pub mod snapshot;
 pub mod types;
 pub mod state;
+#[cfg(feature = "tracing")]
+pub mod telemetry;
 
 #[derive(Default, Clone)]
 pub struct RosMonitor {
     state: Arc<Mutex<state::RosState>>,
-    channel: Arc<Mutex<Option<tokio::sync::broadcast::Sender<types::DiscoveryEvent>>>>,
+    hello: Arc<Mutex<Option<protocol::Hello>>>,
+    last_error: Arc<Mutex<Option<Arc<RosMonitorError>>>>,
+    history: Arc<Mutex<History>>,
+    discovery_duration: Arc<Mutex<Option<Duration>>>,
+    channel: Arc<Mutex<Option<tokio::sync::broadcast::Sender<types::DiscoveryEventWrapper>>>>,
+    diagnostics: Arc<Mutex<Option<tokio::sync::broadcast::Sender<diagnostic::Diagnostic>>>>,
+    shutdown: Arc<tokio::sync::Notify>,
+    finished: Option<tokio::sync::watch::Receiver<()>>,
     task: Option<Arc<AbortJoinHandle>>,
+    // tags the telemetry records, see `RosMonitorConfig::domain_id`
+    domain_id: Option<u32>,
 }
 
 struct AbortJoinHandle(pub tokio::task::JoinHandle<()>);
@@ -22,79 +50,225 @@ impl Drop for AbortJoinHandle {
     }
 }
 
+// How a stream from `RosMonitor::subscribe_since` or `resume` starts: with the
+// events replayed from the history, or with the current state (or the changes
+// to it) as its first `events` events, which share one sequence number.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamStart {
+    Replayed,
+    State { events: usize },
+}
+
+#[derive(Debug, Clone)]
+pub struct RosMonitorConfig {
+    pub max_frame_size: usize,
+    pub channel_capacity: usize,
+    pub history_size: usize,
+    pub shutdown_timeout: Duration,
+    // encoding requested from the spawned process, daemons use their own
+    pub format: protocol::WireFormat,
+    // ROS domain of the spawned process, instead of the inherited `ROS_DOMAIN_ID`,
+    // also added to the telemetry records of its events
+    pub domain_id: Option<u32>,
+    // passed to the spawned process before `-f`, e.g. its node name
+    pub args: Vec<OsString>,
+    // when set, connects to an `intrepid-ros-monitor daemon` listening on this
+    // socket, and only spawns its own process if there's none
+    pub daemon_socket: Option<PathBuf>,
+    // when set, connects to a daemon on another machine over TCP instead,
+    // reconnecting whenever the connection is lost
+    pub remote: Option<remote::RemoteConfig>,
+}
+
+impl Default for RosMonitorConfig {
+    fn default() -> Self {
+        Self {
+            max_frame_size: protocol::DEFAULT_MAX_FRAME_SIZE,
+            channel_capacity: 128,
+            history_size: 1024,
+            shutdown_timeout: Duration::from_secs(5),
+            format: protocol::WireFormat::default(),
+            domain_id: None,
+            args: vec![],
+            daemon_socket: None,
+            remote: None,
+        }
+    }
+}
+
 impl RosMonitor {
     pub fn new(command: impl Into<OsString>) -> Self {
+        Self::with_config(command, RosMonitorConfig::default())
+    }
+
+    pub fn with_config(command: impl Into<OsString>, config: RosMonitorConfig) -> Self {
         use std::process::Stdio;
-        use tokio::io::AsyncReadExt;
         use tokio::process::Command;
 
         let state_arc = Arc::new(Mutex::new(state::RosState::default()));
-        let (channel, _rx) = tokio::sync::broadcast::channel(128);
+        let (channel, _rx) = tokio::sync::broadcast::channel(config.channel_capacity);
         let command = command.into();
         let state_arc_ = state_arc.clone();
+        let history_arc = Arc::new(Mutex::new(History::new(config.history_size)));
+        let history_arc_ = history_arc.clone();
+        let hello_arc = Arc::new(Mutex::new(None));
+        let hello_arc_ = hello_arc.clone();
+        let last_error_arc = Arc::new(Mutex::new(None));
+        let last_error_arc_ = last_error_arc.clone();
         let channel_arc = Arc::new(Mutex::new(Some(channel.clone())));
         let channel_arc_ = channel_arc.clone();
+        let (diagnostics, _rx) = tokio::sync::broadcast::channel(config.channel_capacity);
+        let diagnostics_arc = Arc::new(Mutex::new(Some(diagnostics.clone())));
+        let diagnostics_arc_ = diagnostics_arc.clone();
+        let shutdown = Arc::new(tokio::sync::Notify::new());
+        let shutdown_ = shutdown.clone();
+        let (finished_tx, finished) = tokio::sync::watch::channel(());
+
+        let domain_id = config.domain_id;
 
         let task = AbortJoinHandle(tokio::spawn(async move {
+            let _finished_tx = finished_tx;
             let error: Result<(), RosMonitorError> = async {
                 let channel_ = {
                     let channel = channel_arc_.lock().unwrap();
                     channel.as_ref().unwrap().clone()
                 };
 
-                let mut byte_buffer = Vec::new();
-                let mut bitcode_buffer = bitcode::Buffer::new();
-
+                let mut connect_failed = false;
                 loop {
                     let started_at = std::time::Instant::now();
-                    let mut child = Command::new(&command)
-                        .arg("-f")
-                        .arg("bitcode")
-                        .stdout(Stdio::piped())
-                        .stderr(Stdio::piped())
-                        .spawn()?;
-
-                    let stdout = child.stdout.take().ok_or(RosMonitorError::PipeError)?;
-                    let mut reader = tokio::io::BufReader::new(stdout);
-
-                    loop {
-                        let Ok(size) = reader.read_u32_le().await else { break };
-                        byte_buffer.resize(size as usize, 0);
-                        let Ok(_) = reader.read_exact(&mut byte_buffer).await else { break };
-                        let Ok(event) = bitcode_buffer.decode::<types::DiscoveryEventWrapper>(&byte_buffer) else { break };
-
-                        {
-                            let mut state = state_arc_.lock().unwrap();
-                            let mut new_state = state.clone();
+
+                    let daemon = match (&config.remote, &config.daemon_socket) {
+                        (Some(remote), _) => match remote::connect(remote).await {
+                            Ok(stream) => {
+                                log::debug!("connected to ROS monitor daemon at {}", remote.address);
+                                connect_failed = false;
+                                Some(stream)
+                            }
+                            Err(err) => {
+                                // only warn once while the daemon stays unreachable
+                                if !connect_failed {
+                                    log::warn!("unable to connect to ROS monitor daemon at {}: {}", remote.address, err);
+                                }
+                                connect_failed = true;
+                                *last_error_arc_.lock().unwrap() = Some(Arc::new(RosMonitorError::ConnectError(err)));
+                                tokio::select! {
+                                    _ = tokio::time::sleep(Duration::from_secs(1)) => continue,
+                                    _ = shutdown_.notified() => return Ok(()),
+                                }
+                            }
+                        },
+                        (None, Some(path)) => connect_daemon(path).await,
+                        (None, None) => None,
+                    };
+                    let connected = daemon.is_some();
+
+                    let mut child = None;
+                    let mut stderr_task = None;
+                    let stdout: Box<dyn tokio::io::AsyncRead + Unpin + Send> = match daemon {
+                        Some(stream) => stream,
+                        None => {
+                            let mut process = Command::new(&command);
+                            if let Some(domain_id) = config.domain_id {
+                                process.env("ROS_DOMAIN_ID", domain_id.to_string());
+                            }
+                            let mut process = process
+                                .args(&config.args)
+                                .arg("-f")
+                                .arg(config.format.name())
+                                .stdout(Stdio::piped())
+                                .stderr(Stdio::piped())
+                                .kill_on_drop(true)
+                                .spawn()?;
+
+                            let stdout = process.stdout.take().ok_or(RosMonitorError::PipeError)?;
+                            let stderr = process.stderr.take().ok_or(RosMonitorError::PipeError)?;
+                            stderr_task = Some(tokio::spawn(drain_stderr(stderr, diagnostics.clone())));
+                            child = Some(process);
+                            Box::new(stdout)
+                        }
+                    };
+
+                    let read_stream = async {
+                        let mut reader = protocol::FrameReader::new(tokio::io::BufReader::new(stdout))
+                            .with_max_frame_size(config.max_frame_size);
+
+                        match reader.read_hello().await {
+                            Ok(hello) => {
+                                *hello_arc_.lock().unwrap() = Some(hello);
+                            }
+                            // process exited before the handshake, handled below
+                            Err(protocol::ProtocolError::Io(err)) => {
+                                if connected {
+                                    log::warn!("ROS monitor daemon closed the connection before the handshake (invalid token?): {}", err);
+                                    *last_error_arc_.lock().unwrap() = Some(Arc::new(RosMonitorError::ConnectError(err)));
+                                }
+                                return Ok(None);
+                            }
+                            Err(err @ (protocol::ProtocolError::MissingHello | protocol::ProtocolError::IncompatibleVersion { .. })) => {
+                                return Err(RosMonitorError::from(err));
+                            }
+                            Err(err) => return Ok(Some(err)),
+                        }
+
+                        loop {
+                            let event = match reader.read_event().await {
+                                Ok(Some(event)) => event,
+                                Ok(None) => return Ok(None),
+                                Err(err) => return Ok(Some(err)),
+                            };
+
+                            let mut new_state = state_arc_.lock().unwrap().clone();
                             new_state.update(event.event);
-                            for event in new_state.changes(&state) {
-                                let _ = channel_.send(event);
-                                // yield event;
+                            publish_state(&state_arc_, &history_arc_, &channel_, domain_id, event.ts, new_state);
+                        }
+                    };
+
+                    let mut shutdown_requested = false;
+                    let stream_error: Option<protocol::ProtocolError> = tokio::select! {
+                        result = read_stream => result?,
+                        _ = shutdown_.notified() => {
+                            shutdown_requested = true;
+                            if let Some(child) = &mut child {
+                                terminate(child, config.shutdown_timeout).await?;
                             }
-                            *state = new_state;
+                            None
                         }
-                    }
+                    };
 
-                    {
-                        let mut state = state_arc_.lock().unwrap();
-                        let new_state = state::RosState::default();
-                        for event in new_state.changes(&state) {
-                            let _ = channel_.send(event);
-                            // yield event;
+                    if let Some(err) = stream_error {
+                        // the stream can't be resynchronized, so restart the process
+                        log::warn!("ROS discovery stream error, restarting: {}", err);
+                        if let Some(child) = &mut child {
+                            let _ = child.start_kill();
                         }
-                        *state = new_state;
+                        *last_error_arc_.lock().unwrap() = Some(Arc::new(err.into()));
+                    }
+
+                    publish_state(&state_arc_, &history_arc_, &channel_, domain_id, now_millis(), state::RosState::default());
+
+                    if shutdown_requested {
+                        return Ok(());
                     }
 
                     let elapsed = started_at.elapsed();
-                    if elapsed.as_millis() < 2000 {
-                        let mut stderr = child.stderr.take().ok_or(RosMonitorError::PipeError)?;
-                        let mut result = String::new();
-                        stderr.read_to_string(&mut result).await?;
-                        let status = child.try_wait()?;
-                        Err(RosMonitorError::ProcessExited {
-                            status: status.unwrap_or_default(),
-                            stderr: result,
-                        })?;
+                    match (child, stderr_task) {
+                        (Some(mut child), Some(stderr_task)) if elapsed.as_millis() < 2000 => {
+                            let stderr = stderr_task.await.unwrap_or_default();
+                            let status = child.try_wait()?;
+                            Err(RosMonitorError::ProcessExited {
+                                status: status.unwrap_or_default(),
+                                stderr,
+                            })?;
+                        }
+                        (None, _) => {
+                            // daemon went away, give it a moment before reconnecting or spawning
+                            tokio::select! {
+                                _ = tokio::time::sleep(Duration::from_secs(1)) => {}
+                                _ = shutdown_.notified() => return Ok(()),
+                            }
+                        }
+                        _ => {}
                     }
                 }
             }.await;
@@ -115,22 +289,76 @@ impl RosMonitor {
                     }
                     log::error!("ROS discovery is not available:\n{}{}", error, reason);
                 }
+
+                *last_error_arc_.lock().unwrap() = Some(Arc::new(error));
             }
 
             channel_arc_.lock().unwrap().take().unwrap();
+            diagnostics_arc_.lock().unwrap().take().unwrap();
         }));
 
         Self {
             state: state_arc,
+            hello: hello_arc,
+            last_error: last_error_arc,
+            history: history_arc,
+            discovery_duration: Default::default(),
             channel: channel_arc,
+            diagnostics: diagnostics_arc,
+            shutdown,
+            finished: Some(finished),
             task: Some(Arc::new(task)),
+            domain_id,
         }
     }
 
+    // Creates a monitor without a process behind it, the graph is provided
+    // by the caller through the returned feed (e.g. by the binary itself).
+    pub fn manual(config: RosMonitorConfig) -> (Self, RosMonitorFeed) {
+        let (channel, _rx) = tokio::sync::broadcast::channel(config.channel_capacity);
+        let (diagnostics, _rx) = tokio::sync::broadcast::channel(config.channel_capacity);
+        let monitor = Self {
+            history: Arc::new(Mutex::new(History::new(config.history_size))),
+            channel: Arc::new(Mutex::new(Some(channel))),
+            diagnostics: Arc::new(Mutex::new(Some(diagnostics))),
+            domain_id: config.domain_id,
+            ..Default::default()
+        };
+        (monitor.clone(), RosMonitorFeed { monitor })
+    }
+
+    pub fn hello(&self) -> Option<protocol::Hello> {
+        self.hello.lock().unwrap().clone()
+    }
+
+    pub fn last_error(&self) -> Option<Arc<RosMonitorError>> {
+        self.last_error.lock().unwrap().clone()
+    }
+
+    // Number of events emitted so far, by event type.
+    pub fn event_counts(&self) -> BTreeMap<&'static str, u64> {
+        self.history.lock().unwrap().counts.clone()
+    }
+
+    // How long the last graph discovery took, only known for manual monitors.
+    pub fn discovery_duration(&self) -> Option<Duration> {
+        *self.discovery_duration.lock().unwrap()
+    }
+
+    pub fn state(&self) -> state::RosState {
+        self.state.lock().unwrap().clone()
+    }
+
     pub fn subscribe(&self) -> Result<impl futures::TryStream<Item = Result<types::DiscoveryEvent, RecvError>>, RecvError> {
-        let is_finished = self.task.as_ref().map(|task| task.0.is_finished()).unwrap_or(true);
+        self.subscribe_from(&Default::default())
+    }
 
-        if is_finished {
+    // Like `subscribe`, but the initial events only bring `known` up to date
+    // with the current state, instead of replaying the whole graph.
+    pub fn subscribe_from(&self, known: &state::RosState) -> Result<impl futures::TryStream<Item = Result<types::DiscoveryEvent, RecvError>>, RecvError> {
+        let is_finished = self.task.as_ref().is_some_and(|task| task.0.is_finished());
+
+        if is_finished || self.channel.lock().unwrap().is_none() {
             return Err(RecvError::Closed);
         }
 
@@ -138,7 +366,7 @@ impl RosMonitor {
             let channel = self.channel.lock().unwrap();
             let state = self.state.lock().unwrap();
             (
-                state.changes(&Default::default()),
+                state.changes(known),
                 channel.as_ref().map(|channel| channel.subscribe())
             )
         };
@@ -149,22 +377,361 @@ impl RosMonitor {
                 yield event;
             }
             loop {
-                let event = receiver.recv().await?;
+                let event = match receiver.recv().await {
+                    Err(RecvError::Closed) => break,
+                    event => event?,
+                };
+                yield event.event;
+            }
+        })
+    }
+
+    // Events with their timestamps and sequence numbers. With `since`, a sequence
+    // number, the events published after it are replayed from the history.
+    // Otherwise, or when the history doesn't go back far enough, the stream
+    // starts with the current state, all numbered as the last event published.
+    #[allow(clippy::type_complexity)]
+    pub fn subscribe_since(&self, since: Option<u64>) -> Result<(StreamStart, impl futures::TryStream<Item = Result<types::DiscoveryEventWrapper, RecvError>>), RecvError> {
+        self.resume(since, &Default::default())
+    }
+
+    // Like `subscribe_since`, but when the history doesn't go back far enough,
+    // the stream starts with the events bringing `known` up to date with the
+    // current state, removals included, instead of the whole state.
+    #[allow(clippy::type_complexity)]
+    pub fn resume(&self, since: Option<u64>, known: &state::RosState) -> Result<(StreamStart, impl futures::TryStream<Item = Result<types::DiscoveryEventWrapper, RecvError>>), RecvError> {
+        let is_finished = self.task.as_ref().is_some_and(|task| task.0.is_finished());
+
+        if is_finished || self.channel.lock().unwrap().is_none() {
+            return Err(RecvError::Closed);
+        }
+
+        let (start, initial, receiver) = {
+            let channel = self.channel.lock().unwrap();
+            let state = self.state.lock().unwrap();
+            let history = self.history.lock().unwrap();
+            let (start, initial) = match since.and_then(|since| history.since(since)) {
+                Some(replay) => (StreamStart::Replayed, replay),
+                None => {
+                    let initial: Vec<_> = state.changes(known).into_iter()
+                        .map(|event| types::DiscoveryEventWrapper { ts: history.last_ts, seq: history.last_seq, event })
+                        .collect();
+                    (StreamStart::State { events: initial.len() }, initial)
+                }
+            };
+            (start, initial, channel.as_ref().map(|channel| channel.subscribe()))
+        };
+
+        Ok((start, async_stream::try_stream! {
+            let mut receiver = receiver.ok_or(RecvError::Closed)?;
+            for event in initial {
                 yield event;
             }
+            loop {
+                let event = match receiver.recv().await {
+                    Err(RecvError::Closed) => break,
+                    event => event?,
+                };
+                yield event;
+            }
+        }))
+    }
+
+    // Events matching `filter`, starting with the current state. Unlike `subscribe`,
+    // a consumer that falls behind is resynchronized (with additions and removals
+    // bringing it up to date) instead of getting an error. Ends when the monitor stops.
+    pub fn subscribe_filtered(&self, filter: filter::EventFilter) -> impl futures::Stream<Item = types::DiscoveryEvent> {
+        use futures::TryStreamExt;
+
+        let monitor = self.clone();
+
+        async_stream::stream! {
+            // what was yielded so far, filtered out entities are never part of it
+            let mut known = state::RosState::default();
+
+            loop {
+                let Ok(stream) = monitor.subscribe_from(&known) else { break };
+                let mut stream = std::pin::pin!(stream);
+
+                loop {
+                    match stream.try_next().await {
+                        Ok(Some(event)) => {
+                            if filter.matches(&event) {
+                                known.update(event.clone());
+                                yield event;
+                            }
+                        }
+                        Ok(None) | Err(RecvError::Closed) => return,
+                        Err(RecvError::Lagged(count)) => {
+                            log::warn!("ROS discovery events lagged by {}, resynchronizing", count);
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn diagnostics(&self) -> Result<impl futures::TryStream<Item = Result<diagnostic::Diagnostic, RecvError>>, RecvError> {
+        let mut receiver = self.diagnostics.lock().unwrap().as_ref().ok_or(RecvError::Closed)?.subscribe();
+
+        Ok(async_stream::try_stream! {
+            loop {
+                let diagnostic = match receiver.recv().await {
+                    Err(RecvError::Closed) => break,
+                    diagnostic => diagnostic?,
+                };
+                yield diagnostic;
+            }
         })
     }
+
+    // Stops the process (SIGTERM, then SIGKILL after `shutdown_timeout`),
+    // sends removal events for everything discovered so far, and closes all streams.
+    pub async fn shutdown(&self) {
+        self.shutdown.notify_one();
+        if let Some(mut finished) = self.finished.clone() {
+            let _ = finished.changed().await;
+        }
+    }
+}
+
+pub struct RosMonitorFeed {
+    monitor: RosMonitor,
+}
+
+impl RosMonitorFeed {
+    pub fn set_hello(&self, hello: protocol::Hello) {
+        *self.monitor.hello.lock().unwrap() = Some(hello);
+    }
+
+    pub fn update(&self, ts: u64, new_state: state::RosState) {
+        let Some(channel) = self.monitor.channel.lock().unwrap().clone() else { return };
+        publish_state(&self.monitor.state, &self.monitor.history, &channel, self.monitor.domain_id, ts, new_state);
+    }
+
+    pub fn set_discovery_duration(&self, duration: Duration) {
+        *self.monitor.discovery_duration.lock().unwrap() = Some(duration);
+    }
+
+    pub fn diagnostic(&self, diagnostic: diagnostic::Diagnostic) {
+        if let Some(diagnostics) = self.monitor.diagnostics.lock().unwrap().as_ref() {
+            let _ = diagnostics.send(diagnostic);
+        }
+    }
+}
+
+impl Drop for RosMonitorFeed {
+    fn drop(&mut self) {
+        let channel = self.monitor.channel.lock().unwrap().take();
+        if let Some(channel) = channel {
+            publish_state(&self.monitor.state, &self.monitor.history, &channel, self.monitor.domain_id, now_millis(), state::RosState::default());
+        }
+        self.monitor.diagnostics.lock().unwrap().take();
+    }
+}
+
+#[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+fn publish_state(
+    state_arc: &Mutex<state::RosState>,
+    history_arc: &Mutex<History>,
+    channel: &tokio::sync::broadcast::Sender<types::DiscoveryEventWrapper>,
+    domain_id: Option<u32>,
+    ts: u64,
+    new_state: state::RosState,
+) {
+    let mut state = state_arc.lock().unwrap();
+    let mut history = history_arc.lock().unwrap();
+    for event in new_state.changes(&state) {
+        let event = history.push(ts, event);
+        #[cfg(feature = "tracing")]
+        match domain_id {
+            Some(domain_id) => telemetry::record_domain_event(domain_id, &event),
+            None => telemetry::record_event(&event),
+        }
+        let _ = channel.send(event);
+    }
+    *state = new_state;
+}
+
+#[derive(Default)]
+struct History {
+    events: VecDeque<types::DiscoveryEventWrapper>,
+    capacity: usize,
+    // events up to this sequence number may have been evicted
+    evicted_seq: Option<u64>,
+    last_ts: u64,
+    last_seq: u64,
+    counts: BTreeMap<&'static str, u64>,
+}
+
+impl History {
+    fn new(capacity: usize) -> Self {
+        Self { capacity, ..Default::default() }
+    }
+
+    // Numbers the event and keeps it for replay.
+    fn push(&mut self, ts: u64, event: types::DiscoveryEvent) -> types::DiscoveryEventWrapper {
+        self.last_ts = ts;
+        self.last_seq += 1;
+        *self.counts.entry(event.kind()).or_default() += 1;
+        let event = types::DiscoveryEventWrapper { ts, seq: self.last_seq, event };
+        if self.capacity == 0 {
+            self.evicted_seq = Some(event.seq);
+            return event;
+        }
+        if self.events.len() == self.capacity {
+            self.evicted_seq = self.events.pop_front().map(|event| event.seq);
+        }
+        self.events.push_back(event.clone());
+        event
+    }
+
+    // `None` when events after `seq` were evicted, or when `seq` wasn't handed
+    // out by this history, e.g. by a monitor before a restart.
+    fn since(&self, seq: u64) -> Option<Vec<types::DiscoveryEventWrapper>> {
+        if self.evicted_seq.is_some_and(|evicted_seq| evicted_seq > seq) || seq > self.last_seq {
+            return None;
+        }
+        Some(self.events.iter().filter(|event| event.seq > seq).cloned().collect())
+    }
+}
+
+fn now_millis() -> u64 {
+    std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default().as_millis() as u64
+}
+
+#[cfg(unix)]
+async fn connect_daemon(path: &Path) -> Option<Box<dyn tokio::io::AsyncRead + Unpin + Send>> {
+    match tokio::net::UnixStream::connect(path).await {
+        Ok(stream) => {
+            log::debug!("connected to ROS monitor daemon at {}", path.display());
+            Some(Box::new(stream))
+        }
+        Err(err) => {
+            log::debug!("no ROS monitor daemon at {}: {}", path.display(), err);
+            None
+        }
+    }
+}
+
+#[cfg(not(unix))]
+async fn connect_daemon(_path: &Path) -> Option<Box<dyn tokio::io::AsyncRead + Unpin + Send>> {
+    None
+}
+
+async fn terminate(child: &mut tokio::process::Child, timeout: Duration) -> std::io::Result<()> {
+    #[cfg(unix)]
+    if let Some(pid) = child.id() {
+        // SAFETY: the process is our child and hasn't been reaped yet, so the pid can't be reused
+        unsafe { libc::kill(pid as libc::pid_t, libc::SIGTERM) };
+        if let Ok(status) = tokio::time::timeout(timeout, child.wait()).await {
+            return status.map(|_| ());
+        }
+        log::warn!("ROS discovery process did not exit in {:?}, killing it", timeout);
+    }
+
+    child.kill().await
+}
+
+// Keeps reading stderr for the whole lifetime of the process, so that the
+// pipe never fills up and blocks it. Returns the last lines for error reports.
+async fn drain_stderr(
+    stderr: tokio::process::ChildStderr,
+    diagnostics: tokio::sync::broadcast::Sender<diagnostic::Diagnostic>,
+) -> String {
+    use tokio::io::AsyncBufReadExt;
+
+    const MAX_LINES: usize = 64;
+    let mut tail = std::collections::VecDeque::with_capacity(MAX_LINES);
+    let mut lines = tokio::io::BufReader::new(stderr).lines();
+
+    while let Ok(Some(line)) = lines.next_line().await {
+        let diagnostic = diagnostic::Diagnostic::parse(&line);
+        diagnostic.log();
+        let _ = diagnostics.send(diagnostic);
+
+        if tail.len() == MAX_LINES {
+            tail.pop_front();
+        }
+        tail.push_back(line);
+    }
+
+    tail.into_iter().fold(String::new(), |mut result, line| {
+        result.push_str(&line);
+        result.push('\n');
+        result
+    })
 }
 
 #[derive(Debug, Error)]
-enum RosMonitorError {
+pub enum RosMonitorError {
     #[error("unable to spawn process: {0}")]
     SpawnError(#[from] tokio::io::Error),
+    #[error("unable to connect: {0}")]
+    ConnectError(std::io::Error),
     #[error("unable to read stdout/stderr")]
     PipeError,
+    #[error("protocol error: {0}")]
+    ProtocolError(#[from] protocol::ProtocolError),
     #[error("process exited: {status}\n{stderr}")]
     ProcessExited {
         status: std::process::ExitStatus,
         stderr: String,
     },
 }
+
+#[cfg(test)]
+mod tests {
+    use futures::StreamExt;
+
+    use super::*;
+
+    fn topics(names: &[&str]) -> state::RosState {
+        let mut state = state::RosState::default();
+        for name in names {
+            let properties = types::TopicProperties { types: vec!["std_msgs/msg/String".to_owned()], publishers: vec![], subscribers: vec![] };
+            state.topics.insert(name.to_string(), properties);
+        }
+        state
+    }
+
+    async fn collect(monitor: &RosMonitor, since: Option<u64>, count: usize) -> (StreamStart, Vec<types::DiscoveryEventWrapper>) {
+        let (start, stream) = monitor.subscribe_since(since).unwrap();
+        let events = stream.take(count).map(|event| event.unwrap()).collect().await;
+        (start, events)
+    }
+
+    #[tokio::test]
+    async fn replays_from_the_middle_of_a_batch() {
+        let (monitor, feed) = RosMonitor::manual(Default::default());
+        feed.update(100, topics(&["/a", "/b", "/c"]));
+        feed.update(200, topics(&["/a", "/b", "/c", "/d"]));
+
+        let (start, events) = collect(&monitor, Some(1), 3).await;
+        assert_eq!(start, StreamStart::Replayed);
+        assert!(events[..2].iter().all(|event| event.ts == 100));
+        assert_eq!(events.iter().map(|event| event.seq).collect::<Vec<_>>(), [2, 3, 4]);
+    }
+
+    #[tokio::test]
+    async fn counts_the_events_of_the_current_state() {
+        let (monitor, feed) = RosMonitor::manual(Default::default());
+        let (start, stream) = monitor.subscribe_since(None).unwrap();
+        assert_eq!(start, StreamStart::State { events: 0 });
+
+        feed.update(100, topics(&["/a"]));
+        let events: Vec<_> = stream.take(1).map(|event| event.unwrap()).collect().await;
+        assert_eq!(events[0].seq, 1);
+    }
+
+    #[tokio::test]
+    async fn starts_over_from_an_unknown_sequence_number() {
+        let (monitor, feed) = RosMonitor::manual(Default::default());
+        feed.update(100, topics(&["/a", "/b"]));
+
+        let (start, events) = collect(&monitor, Some(10), 2).await;
+        assert_eq!(start, StreamStart::State { events: 2 });
+        assert!(events.iter().all(|event| event.seq == 2));
+    }
+}
@@ -0,0 +1,83 @@
+use std::fmt::Write;
+
+use crate::RosMonitor;
+
+const EVENT_KINDS: [&str; 6] = [
+    "node_added",
+    "node_removed",
+    "topic_added",
+    "topic_removed",
+    "service_added",
+    "service_removed",
+];
+
+// Renders the graph in the Prometheus text exposition format.
+pub fn render(monitor: &RosMonitor) -> String {
+    let state = monitor.state();
+
+    let mut output = String::new();
+
+    header(&mut output, "ros_monitor_nodes", "gauge", "Number of nodes in the graph.");
+    sample(&mut output, "ros_monitor_nodes", &[], state.nodes.len() as f64);
+    header(&mut output, "ros_monitor_topics", "gauge", "Number of topics in the graph.");
+    sample(&mut output, "ros_monitor_topics", &[], state.topics.len() as f64);
+    header(&mut output, "ros_monitor_services", "gauge", "Number of services in the graph.");
+    sample(&mut output, "ros_monitor_services", &[], state.services.len() as f64);
+
+    header(&mut output, "ros_monitor_topic_publishers", "gauge", "Number of publishers on a topic.");
+    for (name, properties) in &state.topics {
+        sample(&mut output, "ros_monitor_topic_publishers", &[("topic", name)], properties.publishers.len() as f64);
+    }
+    header(&mut output, "ros_monitor_topic_subscribers", "gauge", "Number of subscribers on a topic.");
+    for (name, properties) in &state.topics {
+        sample(&mut output, "ros_monitor_topic_subscribers", &[("topic", name)], properties.subscribers.len() as f64);
+    }
+    header(
+        &mut output,
+        "ros_monitor_topic_qos_incompatible_pairs",
+        "gauge",
+        "Number of publisher/subscriber pairs on a topic with incompatible QoS.",
+    );
+    for (name, properties) in &state.topics {
+        let incompatible = properties.publishers.iter()
+            .flat_map(|publisher| properties.subscribers.iter().map(move |subscriber| (publisher, subscriber)))
+            .filter(|(publisher, subscriber)| !publisher.qos_profile.is_compatible_with(&subscriber.qos_profile))
+            .count();
+        sample(&mut output, "ros_monitor_topic_qos_incompatible_pairs", &[("topic", name)], incompatible as f64);
+    }
+
+    if let Some(duration) = monitor.discovery_duration() {
+        header(&mut output, "ros_monitor_discovery_duration_seconds", "gauge", "Duration of the last graph discovery.");
+        sample(&mut output, "ros_monitor_discovery_duration_seconds", &[], duration.as_secs_f64());
+    }
+
+    let counts = monitor.event_counts();
+    header(&mut output, "ros_monitor_events_total", "counter", "Number of graph changes, by event type.");
+    for kind in EVENT_KINDS {
+        let count = counts.get(kind).copied().unwrap_or_default();
+        sample(&mut output, "ros_monitor_events_total", &[("type", kind)], count as f64);
+    }
+
+    output
+}
+
+fn header(output: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(output, "# HELP {} {}", name, help);
+    let _ = writeln!(output, "# TYPE {} {}", name, kind);
+}
+
+fn sample(output: &mut String, name: &str, labels: &[(&str, &str)], value: f64) {
+    output.push_str(name);
+    if !labels.is_empty() {
+        output.push('{');
+        for (i, (label, value)) in labels.iter().enumerate() {
+            if i > 0 {
+                output.push(',');
+            }
+            let value = value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n");
+            let _ = write!(output, "{}=\"{}\"", label, value);
+        }
+        output.push('}');
+    }
+    let _ = writeln!(output, " {}", value);
+}
@@ -0,0 +1,323 @@
+use std::str::FromStr;
+
+use thiserror::Error;
+
+// ROS 2 names, following the rules of rcl and rmw:
+//
+//   talker                node name, letters, digits and underscores
+//   /robot1/nav2          namespace, absolute
+//   /robot1/odom          fully qualified topic or service name
+//   odom, ~/odom          relative and private names, expanded against a node
+//   {node}/odom           substitutions of the node name and namespace
+//   /odom:=/robot1/odom   remapping
+//   talker:odom:=odom2    remapping for the node `talker` only
+
+const MAX_LENGTH: usize = 255;
+
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum NameError {
+    #[error("name is empty")]
+    Empty,
+    #[error("{0:?} is longer than {MAX_LENGTH} characters")]
+    TooLong(String),
+    #[error("{name:?} contains {character:?}, expected letters, digits, underscores or '/'")]
+    InvalidCharacter { name: String, character: char },
+    #[error("{0:?} has a segment starting with a digit")]
+    StartsWithDigit(String),
+    #[error("{0:?} is not absolute, expected it to start with '/'")]
+    NotAbsolute(String),
+    #[error("{0:?} has an empty segment")]
+    EmptySegment(String),
+    #[error("{0:?} uses '~' other than as its first segment")]
+    MisplacedTilde(String),
+    #[error("{0:?} has an unknown or unbalanced substitution")]
+    InvalidSubstitution(String),
+    #[error("{0:?} is not a remapping, expected `from:=to`")]
+    InvalidRemapping(String),
+}
+
+pub fn validate_node_name(name: &str) -> Result<(), NameError> {
+    if name.is_empty() {
+        return Err(NameError::Empty);
+    }
+    if let Some(character) = name.chars().find(|&c| !is_name_character(c)) {
+        return Err(NameError::InvalidCharacter { name: name.to_owned(), character });
+    }
+    if name.starts_with(|c: char| c.is_ascii_digit()) {
+        return Err(NameError::StartsWithDigit(name.to_owned()));
+    }
+    if name.len() > MAX_LENGTH {
+        return Err(NameError::TooLong(name.to_owned()));
+    }
+    Ok(())
+}
+
+// `/` or absolute segments, without a trailing slash.
+pub fn validate_namespace(namespace: &str) -> Result<(), NameError> {
+    if namespace == "/" {
+        return Ok(());
+    }
+    validate_fully_qualified_name(namespace)
+}
+
+// A fully qualified topic or service name, e.g. `/robot1/odom`.
+pub fn validate_fully_qualified_name(name: &str) -> Result<(), NameError> {
+    if name.is_empty() {
+        return Err(NameError::Empty);
+    }
+    let Some(segments) = name.strip_prefix('/') else {
+        return Err(NameError::NotAbsolute(name.to_owned()));
+    };
+    if let Some(character) = name.chars().find(|&c| !is_name_character(c) && c != '/') {
+        return Err(NameError::InvalidCharacter { name: name.to_owned(), character });
+    }
+    for segment in segments.split('/') {
+        if segment.is_empty() {
+            return Err(NameError::EmptySegment(name.to_owned()));
+        }
+        if segment.starts_with(|c: char| c.is_ascii_digit()) {
+            return Err(NameError::StartsWithDigit(name.to_owned()));
+        }
+    }
+    if name.len() > MAX_LENGTH {
+        return Err(NameError::TooLong(name.to_owned()));
+    }
+    Ok(())
+}
+
+// Splits a node name as given on the command line into `(name, namespace)`,
+// e.g. `/intrepid/_discovery` into `("_discovery", "/intrepid")`. Relative
+// names are in the root namespace.
+pub fn parse_node_name(node: &str) -> Result<(String, String), NameError> {
+    if node.is_empty() {
+        return Err(NameError::Empty);
+    }
+    let absolute = if node.starts_with('/') { node.to_owned() } else { format!("/{}", node) };
+    validate_fully_qualified_name(&absolute)?;
+
+    let (namespace, name) = absolute.rsplit_once('/').unwrap_or_default();
+    let namespace = if namespace.is_empty() { "/" } else { namespace };
+    Ok((name.to_owned(), namespace.to_owned()))
+}
+
+pub fn fully_qualified_name(namespace: &str, name: &str) -> String {
+    if namespace.ends_with('/') {
+        format!("{}{}", namespace, name)
+    } else {
+        format!("{}/{}", namespace, name)
+    }
+}
+
+// Expands a topic or service name used by the node `node_name` in `namespace`
+// into a fully qualified name: `~` is the node itself, `{node}` its name,
+// `{ns}` or `{namespace}` its namespace, and relative names are resolved in
+// the namespace.
+pub fn expand_name(name: &str, node_name: &str, namespace: &str) -> Result<String, NameError> {
+    if name.is_empty() {
+        return Err(NameError::Empty);
+    }
+    validate_node_name(node_name)?;
+    validate_namespace(namespace)?;
+    let misplaced_tilde = match name.strip_prefix('~') {
+        Some(rest) => rest.contains('~') || !(rest.is_empty() || rest.starts_with('/')),
+        None => name.contains('~'),
+    };
+    if misplaced_tilde {
+        return Err(NameError::MisplacedTilde(name.to_owned()));
+    }
+
+    let mut expanded = match name.strip_prefix('~') {
+        Some(rest) => format!("{}{}", fully_qualified_name(namespace, node_name), rest),
+        None => name.to_owned(),
+    };
+    expanded = substitute(&expanded, node_name, namespace)?;
+    if !expanded.starts_with('/') {
+        expanded = fully_qualified_name(namespace, &expanded);
+    }
+
+    validate_fully_qualified_name(&expanded)?;
+    Ok(expanded)
+}
+
+fn substitute(name: &str, node_name: &str, namespace: &str) -> Result<String, NameError> {
+    let invalid = || NameError::InvalidSubstitution(name.to_owned());
+    let mut substituted = String::with_capacity(name.len());
+    let mut rest = name;
+    while let Some(start) = rest.find(['{', '}']) {
+        let (before, from) = rest.split_at(start);
+        substituted.push_str(before);
+        let end = from.find('}').filter(|_| from.starts_with('{')).ok_or_else(invalid)?;
+        match &from[1..end] {
+            "node" => substituted.push_str(node_name),
+            // the root namespace would leave an empty segment
+            "ns" | "namespace" => substituted.push_str(namespace.trim_end_matches('/')),
+            _ => return Err(invalid()),
+        }
+        rest = &from[end + 1..];
+    }
+    substituted.push_str(rest);
+    Ok(substituted)
+}
+
+// `[node:]from:=to`, where `from` is a topic or service name, or `__node` or
+// `__ns` to rename the node itself. With `node`, it only applies to the node
+// of that name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Remapping {
+    pub node: Option<String>,
+    pub from: String,
+    pub to: String,
+}
+
+impl Remapping {
+    fn applies_to(&self, node_name: &str) -> bool {
+        self.node.as_deref().is_none_or(|node| node == node_name)
+    }
+}
+
+impl FromStr for Remapping {
+    type Err = NameError;
+
+    fn from_str(remapping: &str) -> Result<Self, Self::Err> {
+        let invalid = || NameError::InvalidRemapping(remapping.to_owned());
+        let (from, to) = remapping.split_once(":=").ok_or_else(invalid)?;
+        let (node, from) = match from.split_once(':') {
+            Some((node, from)) => {
+                validate_node_name(node)?;
+                (Some(node.to_owned()), from)
+            }
+            None => (None, from),
+        };
+        if from.is_empty() || to.is_empty() {
+            return Err(invalid());
+        }
+        Ok(Self { node, from: from.to_owned(), to: to.to_owned() })
+    }
+}
+
+// Applies the first remapping matching `name` once both are expanded for the
+// node, or returns the expanded name. Remappings for other nodes are skipped.
+pub fn remap(name: &str, remappings: &[Remapping], node_name: &str, namespace: &str) -> Result<String, NameError> {
+    let expanded = expand_name(name, node_name, namespace)?;
+    for remapping in remappings.iter().filter(|remapping| remapping.applies_to(node_name) && !remapping.from.starts_with("__")) {
+        if expand_name(&remapping.from, node_name, namespace)? == expanded {
+            return expand_name(&remapping.to, node_name, namespace);
+        }
+    }
+    Ok(expanded)
+}
+
+// Applies the `__node` and `__ns` remappings to the node's `(name, namespace)`.
+pub fn remap_node(name: &str, namespace: &str, remappings: &[Remapping]) -> Result<(String, String), NameError> {
+    let mut remapped = (name.to_owned(), namespace.to_owned());
+    for remapping in remappings.iter().filter(|remapping| remapping.applies_to(name)) {
+        match remapping.from.as_str() {
+            "__node" | "__name" => {
+                validate_node_name(&remapping.to)?;
+                remapped.0 = remapping.to.clone();
+            }
+            "__ns" => {
+                validate_namespace(&remapping.to)?;
+                remapped.1 = remapping.to.clone();
+            }
+            _ => {}
+        }
+    }
+    Ok(remapped)
+}
+
+fn is_name_character(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn remappings(remappings: &[&str]) -> Vec<Remapping> {
+        remappings.iter().map(|remapping| remapping.parse().unwrap()).collect()
+    }
+
+    fn pair(name: &str, namespace: &str) -> (String, String) {
+        (name.to_owned(), namespace.to_owned())
+    }
+
+    #[test]
+    fn node_name() {
+        assert_eq!(parse_node_name("/intrepid/_discovery").unwrap(), pair("_discovery", "/intrepid"));
+        assert_eq!(parse_node_name("/talker").unwrap(), pair("talker", "/"));
+        assert_eq!(parse_node_name("talker").unwrap(), pair("talker", "/"));
+        assert_eq!(parse_node_name(""), Err(NameError::Empty));
+        assert_eq!(parse_node_name("/intrepid/"), Err(NameError::EmptySegment("/intrepid/".to_owned())));
+        assert_eq!(parse_node_name("/1robot/talker"), Err(NameError::StartsWithDigit("/1robot/talker".to_owned())));
+    }
+
+    #[test]
+    fn expand() {
+        assert_eq!(expand_name("odom", "talker", "/robot1").unwrap(), "/robot1/odom");
+        assert_eq!(expand_name("odom", "talker", "/").unwrap(), "/odom");
+        assert_eq!(expand_name("/odom", "talker", "/robot1").unwrap(), "/odom");
+    }
+
+    #[test]
+    fn expand_private() {
+        assert_eq!(expand_name("~", "talker", "/robot1").unwrap(), "/robot1/talker");
+        assert_eq!(expand_name("~/x", "talker", "/robot1").unwrap(), "/robot1/talker/x");
+        assert_eq!(expand_name("~/x", "talker", "/").unwrap(), "/talker/x");
+        assert_eq!(expand_name("~x", "talker", "/"), Err(NameError::MisplacedTilde("~x".to_owned())));
+        assert_eq!(expand_name("a/~", "talker", "/"), Err(NameError::MisplacedTilde("a/~".to_owned())));
+        assert_eq!(expand_name("~/~", "talker", "/"), Err(NameError::MisplacedTilde("~/~".to_owned())));
+    }
+
+    #[test]
+    fn expand_substitutions() {
+        assert_eq!(expand_name("{node}/odom", "talker", "/robot1").unwrap(), "/robot1/talker/odom");
+        assert_eq!(expand_name("{ns}/odom", "talker", "/robot1").unwrap(), "/robot1/odom");
+        assert_eq!(expand_name("{node}/odom", "talker", "/").unwrap(), "/talker/odom");
+        assert_eq!(expand_name("{ns}/{node}/odom", "talker", "/").unwrap(), "/talker/odom");
+        assert_eq!(expand_name("{namespace}/odom", "talker", "/").unwrap(), "/odom");
+        for name in ["{node/odom", "node}/odom", "{}/odom", "{robot}/odom"] {
+            assert_eq!(expand_name(name, "talker", "/"), Err(NameError::InvalidSubstitution(name.to_owned())));
+        }
+    }
+
+    #[test]
+    fn node_remapping() {
+        let renamed = remappings(&["__node:=listener", "__ns:=/robot1"]);
+        assert_eq!(remap_node("talker", "/", &renamed).unwrap(), pair("listener", "/robot1"));
+        assert_eq!(remap_node("talker", "/", &[]).unwrap(), pair("talker", "/"));
+        assert_eq!(remap_node("talker", "/", &remappings(&["__name:=listener"])).unwrap(), pair("listener", "/"));
+        assert_eq!(remap_node("talker", "/", &remappings(&["__ns:=robot1"])), Err(NameError::NotAbsolute("robot1".to_owned())));
+        assert!(remap_node("talker", "/", &remappings(&["__node:=my-node"])).is_err());
+    }
+
+    #[test]
+    fn topic_remapping() {
+        let remappings = remappings(&["odom:=/robot1/odom_filtered", "__node:=foo"]);
+        assert_eq!(remap("odom", &remappings, "talker", "/robot1").unwrap(), "/robot1/odom_filtered");
+        assert_eq!(remap("/robot1/odom", &remappings, "talker", "/robot1").unwrap(), "/robot1/odom_filtered");
+        assert_eq!(remap("odom", &remappings, "talker", "/robot2").unwrap(), "/robot1/odom_filtered");
+        assert_eq!(remap("/odom", &remappings, "talker", "/robot2").unwrap(), "/odom");
+    }
+
+    #[test]
+    fn node_scoped_remapping() {
+        let remapping: Remapping = "talker:/foo:=/bar".parse().unwrap();
+        assert_eq!(remapping, Remapping { node: Some("talker".to_owned()), from: "/foo".to_owned(), to: "/bar".to_owned() });
+
+        let remappings = remappings(&["talker:/foo:=/bar", "listener:__node:=renamed"]);
+        assert_eq!(remap("/foo", &remappings, "talker", "/").unwrap(), "/bar");
+        assert_eq!(remap("/foo", &remappings, "listener", "/").unwrap(), "/foo");
+        assert_eq!(remap("odom", &remappings, "listener", "/robot1").unwrap(), "/robot1/odom");
+        assert_eq!(remap_node("talker", "/", &remappings).unwrap(), pair("talker", "/"));
+        assert_eq!(remap_node("listener", "/", &remappings).unwrap(), pair("renamed", "/"));
+    }
+
+    #[test]
+    fn invalid_remapping() {
+        assert_eq!("nope".parse::<Remapping>(), Err(NameError::InvalidRemapping("nope".to_owned())));
+        assert_eq!(":=/bar".parse::<Remapping>(), Err(NameError::InvalidRemapping(":=/bar".to_owned())));
+        assert_eq!("talker::=/bar".parse::<Remapping>(), Err(NameError::InvalidRemapping("talker::=/bar".to_owned())));
+        assert!("1talker:/foo:=/bar".parse::<Remapping>().is_err());
+    }
+}
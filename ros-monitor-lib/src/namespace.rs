@@ -0,0 +1,145 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::state::RosState;
+
+// The namespaces of the graph, with the number of entities in each namespace
+// and below. Topics and services belong to the namespace of their name, e.g.
+// `/robot1/odom` to `/robot1`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NamespaceTree {
+    pub namespace: String,
+    pub nodes: usize,
+    pub topics: usize,
+    pub services: usize,
+    // keyed by the last segment of their namespace
+    pub children: BTreeMap<String, NamespaceTree>,
+}
+
+impl NamespaceTree {
+    pub fn from_state(state: &RosState) -> Self {
+        let mut tree = Self { namespace: "/".to_owned(), ..Default::default() };
+        for (namespace, _) in state.nodes.keys() {
+            tree.add(namespace, |tree| tree.nodes += 1);
+        }
+        for name in state.topics.keys() {
+            tree.add(parent(name), |tree| tree.topics += 1);
+        }
+        for name in state.services.keys() {
+            tree.add(parent(name), |tree| tree.services += 1);
+        }
+        tree
+    }
+
+    pub fn subtree(&self, namespace: &str) -> Option<&Self> {
+        segments(namespace).try_fold(self, |tree, segment| tree.children.get(segment))
+    }
+
+    // Folds the namespaces more than `depth` levels below this one into their
+    // ancestor, which keeps counting their entities.
+    pub fn collapse(&mut self, depth: usize) {
+        if depth == 0 {
+            self.children.clear();
+        }
+        for child in self.children.values_mut() {
+            child.collapse(depth.saturating_sub(1));
+        }
+    }
+
+    // Counts an entity in `namespace` and every namespace above it.
+    fn add(&mut self, namespace: &str, count: impl Fn(&mut Self)) {
+        count(self);
+        let mut tree = self;
+        for segment in segments(namespace) {
+            let path = if tree.namespace == "/" { format!("/{}", segment) } else { format!("{}/{}", tree.namespace, segment) };
+            tree = tree.children.entry(segment.to_owned()).or_insert_with(|| Self { namespace: path, ..Default::default() });
+            count(tree);
+        }
+    }
+}
+
+impl RosState {
+    pub fn namespace_tree(&self) -> NamespaceTree {
+        NamespaceTree::from_state(self)
+    }
+}
+
+fn parent(name: &str) -> &str {
+    name.rsplit_once('/').map_or("/", |(parent, _)| parent)
+}
+
+fn segments(namespace: &str) -> impl Iterator<Item = &str> {
+    namespace.split('/').filter(|segment| !segment.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{NodeProperties, ServiceProperties, TopicProperties};
+
+    fn state() -> RosState {
+        let mut state = RosState::default();
+        for (name, namespace) in [("listener", "/"), ("talker", "/robot1"), ("planner", "/robot1/nav2")] {
+            let properties = NodeProperties {
+                enclave: "/".to_owned(),
+                publishers: BTreeMap::new(),
+                subscribers: BTreeMap::new(),
+                clients: BTreeMap::new(),
+                services: BTreeMap::new(),
+                participant: None,
+            };
+            state.nodes.insert((namespace.to_owned(), name.to_owned()), properties);
+        }
+        for name in ["/chatter", "/robot1/odom", "/robot1/nav2/plan", "/robot2/odom"] {
+            state.topics.insert(name.to_owned(), TopicProperties { types: vec![], publishers: vec![], subscribers: vec![] });
+        }
+        state.services.insert("/robot1/nav2/get_plan".to_owned(), ServiceProperties { types: vec![] });
+        state
+    }
+
+    fn counts(tree: &NamespaceTree) -> (&str, usize, usize, usize) {
+        (&tree.namespace, tree.nodes, tree.topics, tree.services)
+    }
+
+    #[test]
+    fn counts_include_descendants() {
+        let tree = state().namespace_tree();
+        assert_eq!(counts(&tree), ("/", 3, 4, 1));
+        assert_eq!(tree.children.keys().collect::<Vec<_>>(), ["robot1", "robot2"]);
+        assert_eq!(counts(&tree.children["robot1"]), ("/robot1", 2, 2, 1));
+        assert_eq!(counts(&tree.children["robot1"].children["nav2"]), ("/robot1/nav2", 1, 1, 1));
+        assert_eq!(counts(&tree.children["robot2"]), ("/robot2", 0, 1, 0));
+        assert!(tree.children["robot2"].children.is_empty());
+    }
+
+    #[test]
+    fn subtree() {
+        let tree = state().namespace_tree();
+        assert_eq!(tree.subtree("/"), Some(&tree));
+        assert_eq!(tree.subtree("/robot1/nav2").map(counts), Some(("/robot1/nav2", 1, 1, 1)));
+        assert_eq!(tree.subtree("/robot1/").map(counts), Some(("/robot1", 2, 2, 1)));
+        assert_eq!(tree.subtree("/robot3"), None);
+        assert_eq!(tree.subtree("/robot1/nav"), None);
+    }
+
+    #[test]
+    fn collapse() {
+        let tree = state().namespace_tree();
+
+        let mut collapsed = tree.clone();
+        collapsed.collapse(1);
+        assert_eq!(collapsed.children.keys().collect::<Vec<_>>(), ["robot1", "robot2"]);
+        assert!(collapsed.children["robot1"].children.is_empty());
+        assert_eq!(counts(&collapsed.children["robot1"]), ("/robot1", 2, 2, 1));
+
+        let mut collapsed = tree.clone();
+        collapsed.collapse(0);
+        assert!(collapsed.children.is_empty());
+        assert_eq!(counts(&collapsed), ("/", 3, 4, 1));
+
+        let mut collapsed = tree.clone();
+        collapsed.collapse(2);
+        assert_eq!(collapsed, tree);
+    }
+}
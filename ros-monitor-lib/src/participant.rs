@@ -0,0 +1,84 @@
+use std::fmt::Write;
+
+use crate::snapshot::hostname;
+use crate::state::RosState;
+use crate::types::ParticipantInfo;
+
+const GUID_PREFIX_SIZE: usize = 12;
+const VENDOR_EPROSIMA: [u8; 2] = [0x01, 0x0f];
+
+// What the GUID prefix at the start of an endpoint GID tells about where its
+// participant runs. Only Fast DDS puts the host id (bytes 2..4) and process id
+// (bytes 4..8) in it, other implementations only identify the participant.
+//
+// Nodes are attributed through their topic endpoints only, rcl has no endpoint
+// info (and so no GID) for services, thus a node without any publisher or
+// subscription (e.g. with `/rosout` and parameter events disabled) is left out.
+// The participant's user data, which some setups fill with the host name, isn't
+// used either: rcl doesn't expose it.
+pub fn from_gid(gid: &[u8]) -> Option<ParticipantInfo> {
+    let prefix = gid.get(..GUID_PREFIX_SIZE)?;
+    if prefix.iter().all(|&byte| byte == 0) {
+        return None;
+    }
+
+    let mut guid_prefix = String::with_capacity(2 * GUID_PREFIX_SIZE);
+    for byte in prefix {
+        let _ = write!(guid_prefix, "{:02x}", byte);
+    }
+    let mut participant = ParticipantInfo { guid_prefix, ..Default::default() };
+    if prefix[..2] == VENDOR_EPROSIMA {
+        participant.host_id = Some(u16::from_le_bytes([prefix[2], prefix[3]]));
+        participant.pid = Some(u32::from_le_bytes([prefix[4], prefix[5], prefix[6], prefix[7]]));
+    }
+    Some(participant)
+}
+
+// Names the host of the nodes running on this machine, recognized by sharing
+// the host id of a participant of this process.
+pub fn name_local_host(state: &mut RosState) {
+    let pid = std::process::id();
+    let local_host_id = state.nodes.values()
+        .filter_map(|node| node.participant.as_ref())
+        .find(|participant| participant.pid == Some(pid))
+        .and_then(|participant| participant.host_id);
+    let Some(local_host_id) = local_host_id else { return };
+
+    let host = hostname();
+    for participant in state.nodes.values_mut().filter_map(|node| node.participant.as_mut()) {
+        if participant.host_id == Some(local_host_id) {
+            participant.host = Some(host.clone());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fast_dds_prefix() {
+        let gid = [0x01, 0x0f, 0x34, 0x12, 0x39, 0x30, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x03];
+        let participant = from_gid(&gid).unwrap();
+        assert_eq!(participant.guid_prefix, "010f34123930000001000000");
+        assert_eq!(participant.host_id, Some(0x1234));
+        assert_eq!(participant.pid, Some(12345));
+        assert_eq!(participant.host_label().as_deref(), Some("host-1234"));
+    }
+
+    #[test]
+    fn other_vendor_prefix() {
+        // Cyclone DDS
+        let gid = [0x01, 0x10, 0x34, 0x12, 0x39, 0x30, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x03];
+        let participant = from_gid(&gid).unwrap();
+        assert_eq!(participant.guid_prefix, "011034123930000001000000");
+        assert_eq!((participant.host_id, participant.pid), (None, None));
+        assert_eq!(participant.host_label(), None);
+    }
+
+    #[test]
+    fn unknown_prefix() {
+        assert_eq!(from_gid(&[0; 16]), None);
+        assert_eq!(from_gid(&[0x01, 0x0f]), None);
+    }
+}
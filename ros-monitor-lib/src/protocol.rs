@@ -0,0 +1,538 @@
+use std::io::Write;
+use std::path::PathBuf;
+
+use bitcode::{Decode, DecodeOwned, Encode};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+use crate::types;
+
+// Written before the hello frame, so that a stream produced by a binary
+// predating the versioned protocol (which starts with a frame length)
+// is detected instead of being decoded as garbage.
+pub const MAGIC: [u8; 4] = *b"IRMP";
+
+// Encoding of the frames, identified by the magic that starts the stream.
+// Bitcode is the most compact, CBOR and MessagePack are for consumers that
+// aren't written in Rust.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum WireFormat {
+    #[default]
+    Bitcode,
+    Cbor,
+    MessagePack,
+}
+
+impl WireFormat {
+    pub fn magic(&self) -> [u8; 4] {
+        match self {
+            Self::Bitcode => MAGIC,
+            Self::Cbor => *b"IRMC",
+            Self::MessagePack => *b"IRMM",
+        }
+    }
+
+    pub fn from_magic(magic: [u8; 4]) -> Option<Self> {
+        [Self::Bitcode, Self::Cbor, Self::MessagePack].into_iter().find(|format| format.magic() == magic)
+    }
+
+    // Value of the binary's `--format` option.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Bitcode => "bitcode",
+            Self::Cbor => "cbor",
+            Self::MessagePack => "msgpack",
+        }
+    }
+}
+
+// Bump `major` on any change to the framing or to existing types,
+// bump `minor` when only new event variants are added.
+pub const PROTOCOL_VERSION: ProtocolVersion = ProtocolVersion { major: 1, minor: 0 };
+
+pub const DEFAULT_MAX_FRAME_SIZE: usize = 16 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Encode, Decode)]
+pub struct ProtocolVersion {
+    pub major: u16,
+    pub minor: u16,
+}
+
+impl ProtocolVersion {
+    pub fn is_compatible(&self, other: &Self) -> bool {
+        self.major == other.major
+    }
+}
+
+impl std::fmt::Display for ProtocolVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Encode, Decode)]
+#[serde(tag = "type", rename = "hello")]
+pub struct Hello {
+    pub protocol_version: ProtocolVersion,
+    pub binary_version: String,
+    pub ros_distro: String,
+    pub rmw_implementation: String,
+}
+
+impl Hello {
+    pub fn new(binary_version: impl Into<String>) -> Self {
+        Self {
+            protocol_version: PROTOCOL_VERSION,
+            binary_version: binary_version.into(),
+            ros_distro: std::env::var("ROS_DISTRO").unwrap_or_default(),
+            rmw_implementation: std::env::var("RMW_IMPLEMENTATION").unwrap_or_default(),
+        }
+    }
+}
+
+// Sent by the client first thing on a TCP connection to a daemon, which
+// drops the connection unless the token matches its own.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Encode, Decode)]
+pub struct Auth {
+    pub token: Option<String>,
+}
+
+// Where `intrepid-ros-monitor daemon` listens by default. One daemon per
+// ROS domain, since a node can only discover the graph of its own domain.
+// Without `XDG_RUNTIME_DIR`, in a directory of the user's own under the
+// temporary directory rather than directly in it, where another user could
+// create the socket first, see `prepare_socket`.
+pub fn default_socket_path() -> PathBuf {
+    let domain_id = std::env::var("ROS_DOMAIN_ID").unwrap_or_else(|_| "0".to_owned());
+    let name = format!("intrepid-ros-monitor-{}.sock", domain_id);
+    match std::env::var_os("XDG_RUNTIME_DIR") {
+        Some(directory) => PathBuf::from(directory).join(name),
+        None => std::env::temp_dir().join(format!("intrepid-ros-monitor-{}", user_id())).join(name),
+    }
+}
+
+#[cfg(unix)]
+fn user_id() -> String {
+    unsafe { libc::getuid() }.to_string()
+}
+
+#[cfg(not(unix))]
+fn user_id() -> String {
+    std::env::var("USERNAME").unwrap_or_default()
+}
+
+// Readies `socket` for a daemon to listen on: creates its directory, only
+// accessible to the user, when missing, and removes the socket left behind by
+// a daemon that didn't exit cleanly. Refuses a directory other users can
+// write to, and anything at `socket` that isn't a socket of the user's own.
+#[cfg(unix)]
+pub fn prepare_socket(socket: &std::path::Path) -> std::io::Result<()> {
+    use std::io::{Error, ErrorKind};
+    use std::os::unix::fs::{DirBuilderExt, FileTypeExt, MetadataExt};
+
+    let uid = unsafe { libc::getuid() };
+    if let Some(directory) = socket.parent().filter(|directory| !directory.as_os_str().is_empty()) {
+        if !directory.exists() {
+            std::fs::DirBuilder::new().recursive(true).mode(0o700).create(directory)?;
+        }
+        let metadata = std::fs::metadata(directory)?;
+        if metadata.uid() != uid && metadata.uid() != 0 {
+            return Err(Error::new(ErrorKind::PermissionDenied, format!("{} belongs to another user", directory.display())));
+        }
+        // unless sticky, like /tmp, where only the owner of a file can remove it
+        if metadata.mode() & 0o022 != 0 && metadata.mode() & 0o1000 == 0 {
+            return Err(Error::new(ErrorKind::PermissionDenied, format!("{} is writable by other users", directory.display())));
+        }
+    }
+
+    match std::fs::symlink_metadata(socket) {
+        Ok(metadata) if metadata.file_type().is_socket() && metadata.uid() == uid => std::fs::remove_file(socket),
+        Ok(_) => Err(Error::new(ErrorKind::AlreadyExists, format!("{} exists and isn't a socket of ours", socket.display()))),
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(()),
+        Err(err) => Err(err),
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum ProtocolError {
+    #[error("i/o error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("stream does not start with a hello frame, binary is likely older than ros-monitor-lib {}", env!("CARGO_PKG_VERSION"))]
+    MissingHello,
+    #[error("incompatible protocol version: ros-monitor-lib {} speaks {PROTOCOL_VERSION}, binary {binary_version} speaks {found}", env!("CARGO_PKG_VERSION"))]
+    IncompatibleVersion {
+        found: ProtocolVersion,
+        binary_version: String,
+    },
+    #[error("connection does not start with an auth frame")]
+    MissingAuth,
+    #[error("invalid token")]
+    InvalidToken,
+    #[error("truncated frame: expected {expected} bytes, received {received}")]
+    TruncatedFrame {
+        expected: usize,
+        received: usize,
+    },
+    #[error("frame of {size} bytes exceeds the limit of {limit} bytes")]
+    FrameTooLarge {
+        size: usize,
+        limit: usize,
+    },
+    #[error("unable to decode frame of {size} bytes: {source}")]
+    Decode {
+        size: usize,
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+}
+
+pub struct FrameWriter<W> {
+    writer: W,
+    format: WireFormat,
+    bitcode_buffer: bitcode::Buffer,
+    serde_buffer: Vec<u8>,
+}
+
+impl<W: Write> FrameWriter<W> {
+    pub fn new(writer: W) -> Self {
+        Self::with_format(writer, WireFormat::Bitcode)
+    }
+
+    pub fn with_format(writer: W, format: WireFormat) -> Self {
+        Self { writer, format, bitcode_buffer: bitcode::Buffer::new(), serde_buffer: Vec::new() }
+    }
+
+    pub fn write_hello(&mut self, hello: &Hello) -> std::io::Result<()> {
+        self.writer.write_all(&self.format.magic())?;
+        self.write_value(hello)
+    }
+
+    pub fn write_auth(&mut self, auth: &Auth) -> std::io::Result<()> {
+        self.writer.write_all(&self.format.magic())?;
+        self.write_value(auth)
+    }
+
+    pub fn write_event(&mut self, event: &types::DiscoveryEventWrapper) -> std::io::Result<()> {
+        self.write_value(event)
+    }
+
+    fn write_value<T: Encode + Serialize>(&mut self, value: &T) -> std::io::Result<()> {
+        let buffer = match self.format {
+            WireFormat::Bitcode => return write_frame(&mut self.writer, self.bitcode_buffer.encode(value)),
+            WireFormat::Cbor => {
+                self.serde_buffer.clear();
+                ciborium::into_writer(value, &mut self.serde_buffer).map_err(std::io::Error::other)?;
+                &self.serde_buffer
+            }
+            WireFormat::MessagePack => {
+                self.serde_buffer.clear();
+                // with field names, as the types rely on serde's internally tagged enums
+                rmp_serde::encode::write_named(&mut self.serde_buffer, value).map_err(std::io::Error::other)?;
+                &self.serde_buffer
+            }
+        };
+        write_frame(&mut self.writer, buffer)
+    }
+
+    pub fn flush(&mut self) -> std::io::Result<()> {
+        self.writer.flush()
+    }
+
+    pub fn get_ref(&self) -> &W {
+        &self.writer
+    }
+
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.writer
+    }
+}
+
+fn write_frame(writer: &mut impl Write, buffer: &[u8]) -> std::io::Result<()> {
+    writer.write_all(&(buffer.len() as u32).to_le_bytes())?;
+    writer.write_all(buffer)
+}
+
+pub struct FrameReader<R> {
+    reader: R,
+    format: WireFormat,
+    byte_buffer: Vec<u8>,
+    bitcode_buffer: bitcode::Buffer,
+    max_frame_size: usize,
+    peer: Option<Hello>,
+}
+
+impl<R: AsyncRead + Unpin> FrameReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            format: WireFormat::Bitcode,
+            byte_buffer: Vec::new(),
+            bitcode_buffer: bitcode::Buffer::new(),
+            max_frame_size: DEFAULT_MAX_FRAME_SIZE,
+            peer: None,
+        }
+    }
+
+    pub fn with_max_frame_size(mut self, max_frame_size: usize) -> Self {
+        self.max_frame_size = max_frame_size;
+        self
+    }
+
+    pub fn peer(&self) -> Option<&Hello> {
+        self.peer.as_ref()
+    }
+
+    // Known once the hello (or auth) frame has been read.
+    pub fn format(&self) -> WireFormat {
+        self.format
+    }
+
+    pub async fn read_hello(&mut self) -> Result<Hello, ProtocolError> {
+        let mut magic = [0; 4];
+        self.reader.read_exact(&mut magic).await?;
+        self.format = WireFormat::from_magic(magic).ok_or(ProtocolError::MissingHello)?;
+
+        if !self.read_frame().await? {
+            return Err(ProtocolError::TruncatedFrame { expected: 4, received: 0 });
+        }
+        let hello = self.decode::<Hello>()?;
+        if !PROTOCOL_VERSION.is_compatible(&hello.protocol_version) {
+            return Err(ProtocolError::IncompatibleVersion {
+                found: hello.protocol_version,
+                binary_version: hello.binary_version,
+            });
+        }
+
+        self.peer = Some(hello.clone());
+        Ok(hello)
+    }
+
+    pub async fn read_auth(&mut self) -> Result<Auth, ProtocolError> {
+        let mut magic = [0; 4];
+        self.reader.read_exact(&mut magic).await?;
+        self.format = WireFormat::from_magic(magic).ok_or(ProtocolError::MissingAuth)?;
+
+        if !self.read_frame().await? {
+            return Err(ProtocolError::TruncatedFrame { expected: 4, received: 0 });
+        }
+        self.decode::<Auth>()
+    }
+
+    // Returns `None` when the stream ends cleanly on a frame boundary.
+    pub async fn read_event(&mut self) -> Result<Option<types::DiscoveryEventWrapper>, ProtocolError> {
+        loop {
+            if !self.read_frame().await? {
+                return Ok(None);
+            }
+            match self.decode::<types::DiscoveryEventWrapper>() {
+                Ok(event) => return Ok(Some(event)),
+                // a newer binary may send event variants we don't know about yet
+                Err(err) if self.peer_is_newer() => {
+                    log::debug!("skipping undecodable frame from newer binary: {}", err);
+                }
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    fn peer_is_newer(&self) -> bool {
+        self.peer.as_ref().is_some_and(|peer| peer.protocol_version.minor > PROTOCOL_VERSION.minor)
+    }
+
+    fn decode<T: DecodeOwned + DeserializeOwned>(&mut self) -> Result<T, ProtocolError> {
+        let size = self.byte_buffer.len();
+        let result = match self.format {
+            WireFormat::Bitcode => self.bitcode_buffer.decode(&self.byte_buffer).map_err(Into::into),
+            WireFormat::Cbor => ciborium::from_reader(self.byte_buffer.as_slice()).map_err(Into::into),
+            WireFormat::MessagePack => rmp_serde::from_slice(&self.byte_buffer).map_err(Into::into),
+        };
+        result.map_err(|source| ProtocolError::Decode { size, source })
+    }
+
+    async fn read_frame(&mut self) -> Result<bool, ProtocolError> {
+        let mut size = [0; 4];
+        let received = read_full(&mut self.reader, &mut size).await?;
+        if received == 0 {
+            return Ok(false);
+        }
+        if received < size.len() {
+            return Err(ProtocolError::TruncatedFrame { expected: size.len(), received });
+        }
+
+        let size = u32::from_le_bytes(size) as usize;
+        if size > self.max_frame_size {
+            return Err(ProtocolError::FrameTooLarge { size, limit: self.max_frame_size });
+        }
+
+        self.byte_buffer.resize(size, 0);
+        let received = read_full(&mut self.reader, &mut self.byte_buffer).await?;
+        if received < size {
+            return Err(ProtocolError::TruncatedFrame { expected: size, received });
+        }
+        Ok(true)
+    }
+}
+
+// Like `read_exact`, but reports how much was read before EOF.
+async fn read_full(reader: &mut (impl AsyncRead + Unpin), buffer: &mut [u8]) -> std::io::Result<usize> {
+    let mut received = 0;
+    while received < buffer.len() {
+        match reader.read(&mut buffer[received..]).await? {
+            0 => break,
+            n => received += n,
+        }
+    }
+    Ok(received)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{DiscoveryEvent, DiscoveryEventWrapper};
+
+    const FORMATS: [WireFormat; 3] = [WireFormat::Bitcode, WireFormat::Cbor, WireFormat::MessagePack];
+
+    fn hello(major: u16, minor: u16) -> Hello {
+        Hello {
+            protocol_version: ProtocolVersion { major, minor },
+            binary_version: "0.0.0".to_owned(),
+            ros_distro: "jazzy".to_owned(),
+            rmw_implementation: "rmw_fastrtps_cpp".to_owned(),
+        }
+    }
+
+    fn event(seq: u64) -> DiscoveryEventWrapper {
+        DiscoveryEventWrapper { ts: 100, seq, event: DiscoveryEvent::TopicRemoved { name: "/chatter".to_owned() } }
+    }
+
+    // A stream in `format` starting with `hello`, with an undecodable frame
+    // between two events.
+    fn stream(format: WireFormat, hello: &Hello) -> Vec<u8> {
+        let mut writer = FrameWriter::with_format(Vec::new(), format);
+        writer.write_hello(hello).unwrap();
+        writer.write_event(&event(1)).unwrap();
+        write_frame(writer.get_mut(), &[0xc1]).unwrap();
+        writer.write_event(&event(2)).unwrap();
+        writer.writer
+    }
+
+    #[tokio::test]
+    async fn round_trip() {
+        for format in FORMATS {
+            let mut writer = FrameWriter::with_format(Vec::new(), format);
+            writer.write_hello(&Hello::new("0.0.0")).unwrap();
+            writer.write_event(&event(1)).unwrap();
+            assert_eq!(writer.get_ref()[..4], format.magic());
+
+            let bytes = writer.writer;
+            let mut reader = FrameReader::new(bytes.as_slice());
+            assert_eq!(reader.read_hello().await.unwrap(), Hello::new("0.0.0"));
+            assert_eq!(reader.format(), format);
+            assert_eq!(reader.read_event().await.unwrap(), Some(event(1)));
+            assert_eq!(reader.read_event().await.unwrap(), None);
+        }
+    }
+
+    #[tokio::test]
+    async fn stream_without_hello() {
+        // as written by a binary predating the hello, starting with a frame length
+        let mut writer = FrameWriter::new(Vec::new());
+        writer.write_event(&event(1)).unwrap();
+        let bytes = writer.writer;
+        let result = FrameReader::new(bytes.as_slice()).read_hello().await;
+        assert!(matches!(result, Err(ProtocolError::MissingHello)));
+    }
+
+    #[tokio::test]
+    async fn incompatible_version() {
+        for format in FORMATS {
+            let bytes = stream(format, &hello(PROTOCOL_VERSION.major + 1, 0));
+            let result = FrameReader::new(bytes.as_slice()).read_hello().await;
+            let Err(ProtocolError::IncompatibleVersion { found, binary_version }) = result else { panic!("{:?}", result) };
+            assert_eq!(found, ProtocolVersion { major: PROTOCOL_VERSION.major + 1, minor: 0 });
+            assert_eq!(binary_version, "0.0.0");
+        }
+    }
+
+    #[tokio::test]
+    async fn skips_unknown_frames_from_newer_minor_version() {
+        for format in FORMATS {
+            let bytes = stream(format, &hello(PROTOCOL_VERSION.major, PROTOCOL_VERSION.minor + 1));
+            let mut reader = FrameReader::new(bytes.as_slice());
+            reader.read_hello().await.unwrap();
+            assert_eq!(reader.read_event().await.unwrap(), Some(event(1)));
+            assert_eq!(reader.read_event().await.unwrap(), Some(event(2)));
+            assert_eq!(reader.read_event().await.unwrap(), None);
+        }
+    }
+
+    #[tokio::test]
+    async fn fails_on_unknown_frames_from_same_version() {
+        for format in FORMATS {
+            let bytes = stream(format, &hello(PROTOCOL_VERSION.major, PROTOCOL_VERSION.minor));
+            let mut reader = FrameReader::new(bytes.as_slice());
+            reader.read_hello().await.unwrap();
+            assert_eq!(reader.read_event().await.unwrap(), Some(event(1)));
+            assert!(matches!(reader.read_event().await, Err(ProtocolError::Decode { size: 1, .. })));
+        }
+    }
+
+    #[tokio::test]
+    async fn truncated_frame_header() {
+        let mut reader = FrameReader::new([8, 0].as_slice());
+        let result = reader.read_event().await;
+        assert!(matches!(result, Err(ProtocolError::TruncatedFrame { expected: 4, received: 2 })));
+    }
+
+    #[tokio::test]
+    async fn truncated_frame_body() {
+        let mut reader = FrameReader::new([8, 0, 0, 0, 1, 2, 3].as_slice());
+        let result = reader.read_event().await;
+        assert!(matches!(result, Err(ProtocolError::TruncatedFrame { expected: 8, received: 3 })));
+    }
+
+    #[tokio::test]
+    async fn frame_too_large() {
+        let mut reader = FrameReader::new([0xff, 0xff, 0xff, 0xff, 1, 2, 3].as_slice()).with_max_frame_size(1024);
+        let result = reader.read_event().await;
+        assert!(matches!(result, Err(ProtocolError::FrameTooLarge { size: 0xffff_ffff, limit: 1024 })));
+        // rejected before the buffer is grown to the announced size
+        assert_eq!(reader.byte_buffer.capacity(), 0);
+    }
+
+    #[tokio::test]
+    async fn undecodable_frame() {
+        let mut reader = FrameReader::new([3, 0, 0, 0, 0xc1, 0xc1, 0xc1].as_slice());
+        let result = reader.read_event().await;
+        assert!(matches!(result, Err(ProtocolError::Decode { size: 3, .. })));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn prepares_only_a_private_socket() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let directory = std::env::temp_dir().join(format!("intrepid-ros-monitor-test-{}", std::process::id()));
+        let socket = directory.join("private").join("monitor.sock");
+        prepare_socket(&socket).unwrap();
+        let mode = std::fs::metadata(socket.parent().unwrap()).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o700);
+
+        // left behind by a daemon
+        drop(std::os::unix::net::UnixListener::bind(&socket).unwrap());
+        prepare_socket(&socket).unwrap();
+        assert!(!socket.exists());
+
+        std::fs::write(&socket, "").unwrap();
+        assert_eq!(prepare_socket(&socket).unwrap_err().kind(), std::io::ErrorKind::AlreadyExists);
+
+        let shared = directory.join("shared");
+        std::fs::create_dir(&shared).unwrap();
+        std::fs::set_permissions(&shared, std::fs::Permissions::from_mode(0o777)).unwrap();
+        let result = prepare_socket(&shared.join("monitor.sock"));
+        assert_eq!(result.unwrap_err().kind(), std::io::ErrorKind::PermissionDenied);
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
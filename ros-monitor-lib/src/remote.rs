@@ -0,0 +1,128 @@
+use std::path::PathBuf;
+
+use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+use crate::protocol::{self, Auth, ProtocolError};
+
+// Connection to an `intrepid-ros-monitor daemon --tcp` running on another
+// machine, for networks where DDS discovery doesn't reach it.
+#[derive(Debug, Clone)]
+pub struct RemoteConfig {
+    // `host:port`
+    pub address: String,
+    pub token: Option<String>,
+    pub tls: Option<TlsConfig>,
+}
+
+#[derive(Debug, Clone)]
+pub struct TlsConfig {
+    // PEM file with the certificates trusted to sign the daemon's certificate
+    pub ca_file: PathBuf,
+    // name to verify the certificate against, host part of the address if unset
+    pub server_name: Option<String>,
+}
+
+impl RemoteConfig {
+    pub fn new(address: impl Into<String>) -> Self {
+        Self { address: address.into(), token: None, tls: None }
+    }
+}
+
+pub(crate) async fn connect(remote: &RemoteConfig) -> std::io::Result<Box<dyn AsyncRead + Unpin + Send>> {
+    let stream = TcpStream::connect(&remote.address).await?;
+    stream.set_nodelay(true)?;
+
+    match &remote.tls {
+        None => send_auth(stream, remote).await,
+        #[cfg(feature = "tls")]
+        Some(tls) => send_auth(tls::connect(stream, &remote.address, tls).await?, remote).await,
+        #[cfg(not(feature = "tls"))]
+        Some(_) => Err(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "ros-monitor-lib is built without the `tls` feature",
+        )),
+    }
+}
+
+async fn send_auth<S>(mut stream: S, remote: &RemoteConfig) -> std::io::Result<Box<dyn AsyncRead + Unpin + Send>>
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    let mut writer = protocol::FrameWriter::new(Vec::new());
+    writer.write_auth(&Auth { token: remote.token.clone() })?;
+    stream.write_all(writer.get_ref()).await?;
+    stream.flush().await?;
+    // the write half is kept alive with the stream, closing it would end a TLS session
+    Ok(Box::new(stream))
+}
+
+// Server side of the handshake, reads the auth frame and checks its token.
+pub async fn authenticate(stream: &mut (impl AsyncRead + Unpin), token: Option<&str>) -> Result<(), ProtocolError> {
+    let auth = protocol::FrameReader::new(stream).with_max_frame_size(4096).read_auth().await?;
+    match token {
+        None => Ok(()),
+        Some(token) if auth.token.as_deref().is_some_and(|received| constant_time_eq(received, token)) => Ok(()),
+        Some(_) => Err(ProtocolError::InvalidToken),
+    }
+}
+
+fn constant_time_eq(a: &str, b: &str) -> bool {
+    a.len() == b.len() && a.bytes().zip(b.bytes()).fold(0, |acc, (a, b)| acc | (a ^ b)) == 0
+}
+
+#[cfg(feature = "tls")]
+pub mod tls {
+    use std::path::Path;
+    use std::sync::Arc;
+
+    use tokio::net::TcpStream;
+    use tokio_rustls::rustls;
+    use tokio_rustls::rustls::pki_types::pem::PemObject;
+    use tokio_rustls::rustls::pki_types::{CertificateDer, PrivateKeyDer, ServerName};
+
+    pub use tokio_rustls::TlsAcceptor;
+
+    use super::TlsConfig;
+
+    pub(crate) async fn connect(
+        stream: TcpStream,
+        address: &str,
+        tls: &TlsConfig,
+    ) -> std::io::Result<tokio_rustls::client::TlsStream<TcpStream>> {
+        let mut roots = rustls::RootCertStore::empty();
+        for cert in load_certs(&tls.ca_file)? {
+            roots.add(cert).map_err(std::io::Error::other)?;
+        }
+        let config = rustls::ClientConfig::builder().with_root_certificates(roots).with_no_client_auth();
+
+        let server_name = match &tls.server_name {
+            Some(name) => name.clone(),
+            None => address.rsplit_once(':').map_or(address, |(host, _)| host).trim_matches(['[', ']']).to_owned(),
+        };
+        let server_name = ServerName::try_from(server_name)
+            .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidInput, err))?;
+
+        tokio_rustls::TlsConnector::from(Arc::new(config)).connect(server_name, stream).await
+    }
+
+    // Acceptor for the daemon, from PEM encoded certificate chain and private key.
+    pub fn acceptor(cert_file: &Path, key_file: &Path) -> std::io::Result<TlsAcceptor> {
+        let certs = load_certs(cert_file)?;
+        let key = PrivateKeyDer::from_pem_file(key_file).map_err(|err| {
+            std::io::Error::new(std::io::ErrorKind::InvalidData, format!("{}: {}", key_file.display(), err))
+        })?;
+
+        let config = rustls::ServerConfig::builder()
+            .with_no_client_auth()
+            .with_single_cert(certs, key)
+            .map_err(std::io::Error::other)?;
+        Ok(TlsAcceptor::from(Arc::new(config)))
+    }
+
+    fn load_certs(path: &Path) -> std::io::Result<Vec<CertificateDer<'static>>> {
+        CertificateDer::pem_file_iter(path)
+            .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+            .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, format!("{}: {}", path.display(), err)))
+    }
+}
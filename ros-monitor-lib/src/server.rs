@@ -0,0 +1,285 @@
+use std::collections::BTreeMap;
+use std::convert::Infallible;
+
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::{Path, Query, State};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::{Json, Router};
+use futures::{Stream, StreamExt, TryStreamExt};
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::domains::MultiDomainMonitor;
+use crate::filter::{parse_patterns, EventFilter};
+use crate::namespace::NamespaceTree;
+use crate::state::RosState;
+use crate::{metrics, types, RosMonitor, StreamStart};
+
+// JSON API over the graph known to `monitor`:
+//
+//   GET /nodes                  all nodes
+//   GET /nodes/{ns}/{name}      one node, `ns` may contain several segments
+//   GET /topics                 all topics
+//   GET /topics/{name}          one topic, without the leading slash
+//   GET /services               all services
+//   GET /services/{name}        one service, without the leading slash
+//   GET /namespaces             namespace tree with entity counts, `?depth=..` collapses
+//                               deeper namespaces into their ancestors
+//   GET /namespaces/{ns}        same for one namespace and below
+//   GET /events                 websocket, current state followed by changes,
+//                               optionally filtered with `?node=..&topic=..&service=..`,
+//                               or `?namespace=..` for everything in that namespace
+//   GET /events/sse             same as server-sent events, named after the event type
+//                               with `seq` as the id (not `ts`, which the events of one
+//                               discovery pass share); reconnecting with `Last-Event-ID`
+//                               replays missed events, or sends a `reset` event followed
+//                               by the current state when they're no longer available
+//   GET /metrics                graph metrics in the Prometheus text exposition format
+pub fn router(monitor: RosMonitor) -> Router {
+    Router::new()
+        .route("/nodes", get(nodes))
+        .route("/nodes/{*path}", get(node))
+        .route("/topics", get(topics))
+        .route("/topics/{*name}", get(topic))
+        .route("/services", get(services))
+        .route("/services/{*name}", get(service))
+        .route("/namespaces", get(namespaces))
+        .route("/namespaces/{*namespace}", get(namespace))
+        .route("/events", get(events))
+        .route("/events/sse", get(events_sse))
+        .route("/metrics", get(metrics))
+        .with_state(monitor)
+}
+
+// The routes of `router` for each domain under `/domains/{domain_id}`, and
+// the graphs of all domains at `/domains`.
+pub fn domains_router(monitor: MultiDomainMonitor) -> Router {
+    let mut router = Router::new()
+        .route("/domains", get(domains))
+        .with_state(monitor.clone());
+    for (domain_id, monitor) in monitor.monitors() {
+        router = router.nest(&format!("/domains/{}", domain_id), self::router(monitor.clone()));
+    }
+    router
+}
+
+#[derive(Debug, Serialize)]
+pub struct NodeEntry {
+    pub name: String,
+    pub namespace: String,
+    #[serde(flatten)]
+    pub properties: types::NodeProperties,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TopicEntry {
+    pub name: String,
+    #[serde(flatten)]
+    pub properties: types::TopicProperties,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ServiceEntry {
+    pub name: String,
+    #[serde(flatten)]
+    pub properties: types::ServiceProperties,
+}
+
+// Comma separated glob patterns, see `filter::Pattern`, replacing those of
+// `namespace` for their kind.
+#[derive(Debug, Default, Deserialize)]
+pub struct FilterQuery {
+    pub namespace: Option<String>,
+    pub node: Option<String>,
+    pub topic: Option<String>,
+    pub service: Option<String>,
+}
+
+impl From<FilterQuery> for EventFilter {
+    fn from(query: FilterQuery) -> Self {
+        let filter = query.namespace.as_deref().map(EventFilter::namespace).unwrap_or_default();
+        Self {
+            nodes: query.node.as_deref().map(parse_patterns).or(filter.nodes),
+            topics: query.topic.as_deref().map(parse_patterns).or(filter.topics),
+            services: query.service.as_deref().map(parse_patterns).or(filter.services),
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct DepthQuery {
+    pub depth: Option<usize>,
+}
+
+pub struct NotFound(String);
+
+impl IntoResponse for NotFound {
+    fn into_response(self) -> Response {
+        (StatusCode::NOT_FOUND, Json(serde_json::json!({ "error": self.0 }))).into_response()
+    }
+}
+
+async fn domains(State(monitor): State<MultiDomainMonitor>) -> Json<BTreeMap<u32, RosState>> {
+    Json(monitor.state())
+}
+
+async fn nodes(State(monitor): State<RosMonitor>) -> Json<Vec<NodeEntry>> {
+    let nodes = monitor.state().nodes.into_iter()
+        .map(|((namespace, name), properties)| NodeEntry { name, namespace, properties })
+        .collect();
+    Json(nodes)
+}
+
+async fn node(State(monitor): State<RosMonitor>, Path(path): Path<String>) -> Result<Json<NodeEntry>, NotFound> {
+    let (namespace, name) = match path.trim_matches('/').rsplit_once('/') {
+        Some((namespace, name)) => (format!("/{}", namespace), name.to_owned()),
+        None => ("/".to_owned(), path.trim_matches('/').to_owned()),
+    };
+
+    let mut state = monitor.state();
+    let properties = state.nodes.remove(&(namespace.clone(), name.clone()))
+        .ok_or_else(|| NotFound(format!("node {} not found in namespace {}", name, namespace)))?;
+    Ok(Json(NodeEntry { name, namespace, properties }))
+}
+
+async fn topics(State(monitor): State<RosMonitor>) -> Json<Vec<TopicEntry>> {
+    let topics: Vec<_> = monitor.state().topics.into_iter()
+        .map(|(name, properties)| TopicEntry { name, properties })
+        .collect();
+    Json(topics)
+}
+
+async fn topic(State(monitor): State<RosMonitor>, Path(name): Path<String>) -> Result<Json<TopicEntry>, NotFound> {
+    let name = format!("/{}", name.trim_start_matches('/'));
+    let properties = monitor.state().topics.remove(&name)
+        .ok_or_else(|| NotFound(format!("topic {} not found", name)))?;
+    Ok(Json(TopicEntry { name, properties }))
+}
+
+async fn services(State(monitor): State<RosMonitor>) -> Json<Vec<ServiceEntry>> {
+    let services: Vec<_> = monitor.state().services.into_iter()
+        .map(|(name, properties)| ServiceEntry { name, properties })
+        .collect();
+    Json(services)
+}
+
+async fn service(State(monitor): State<RosMonitor>, Path(name): Path<String>) -> Result<Json<ServiceEntry>, NotFound> {
+    let name = format!("/{}", name.trim_start_matches('/'));
+    let properties = monitor.state().services.remove(&name)
+        .ok_or_else(|| NotFound(format!("service {} not found", name)))?;
+    Ok(Json(ServiceEntry { name, properties }))
+}
+
+async fn namespaces(State(monitor): State<RosMonitor>, Query(query): Query<DepthQuery>) -> Json<NamespaceTree> {
+    let mut tree = monitor.state().namespace_tree();
+    if let Some(depth) = query.depth {
+        tree.collapse(depth);
+    }
+    Json(tree)
+}
+
+async fn namespace(
+    State(monitor): State<RosMonitor>,
+    Path(namespace): Path<String>,
+    Query(query): Query<DepthQuery>,
+) -> Result<Json<NamespaceTree>, NotFound> {
+    let namespace = format!("/{}", namespace.trim_start_matches('/'));
+    let mut tree = monitor.state().namespace_tree().subtree(&namespace).cloned()
+        .ok_or_else(|| NotFound(format!("namespace {} not found", namespace)))?;
+    if let Some(depth) = query.depth {
+        tree.collapse(depth);
+    }
+    Ok(Json(tree))
+}
+
+async fn events(State(monitor): State<RosMonitor>, Query(filter): Query<FilterQuery>, ws: WebSocketUpgrade) -> Response {
+    ws.on_upgrade(move |socket| send_events(socket, monitor, filter.into()))
+}
+
+async fn send_events(mut socket: WebSocket, monitor: RosMonitor, filter: EventFilter) {
+    let stream = monitor.subscribe_filtered(filter);
+    let mut stream = std::pin::pin!(stream);
+
+    loop {
+        tokio::select! {
+            event = stream.next() => {
+                let Some(event) = event else { break };
+                let message = Message::Text(serde_json::to_string(&event).unwrap().into());
+                if socket.send(message).await.is_err() {
+                    return;
+                }
+            }
+            message = socket.recv() => {
+                // incoming messages are ignored, we only care about the socket closing
+                if let None | Some(Err(_)) | Some(Ok(Message::Close(_))) = message {
+                    return;
+                }
+            }
+        }
+    }
+
+    let _ = socket.send(Message::Close(None)).await;
+}
+
+async fn events_sse(
+    State(monitor): State<RosMonitor>,
+    Query(filter): Query<FilterQuery>,
+    headers: HeaderMap,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let filter = EventFilter::from(filter);
+    let last_event_id = headers.get("last-event-id")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<u64>().ok());
+
+    let stream = async_stream::stream! {
+        let mut since = last_event_id;
+
+        loop {
+            let Ok((start, stream)) = monitor.subscribe_since(since) else { break };
+            // the current state shares one sequence number, resuming from it
+            // would skip the rest, so it goes without ids
+            let mut initial = match start {
+                StreamStart::Replayed => 0,
+                StreamStart::State { events } => {
+                    if since.is_some() {
+                        // the empty id makes a reconnecting client start over
+                        yield Ok(Event::default().event("reset").id("").data("{}"));
+                    }
+                    events
+                }
+            };
+            let mut stream = std::pin::pin!(stream);
+
+            loop {
+                match stream.try_next().await {
+                    Ok(Some(event)) => {
+                        since = Some(event.seq);
+                        let is_initial = initial > 0;
+                        initial = initial.saturating_sub(1);
+                        if filter.matches(&event.event) {
+                            let mut sse_event = Event::default()
+                                .event(event.event.kind())
+                                .data(serde_json::to_string(&event).unwrap());
+                            if !is_initial {
+                                sse_event = sse_event.id(event.seq.to_string());
+                            }
+                            yield Ok(sse_event);
+                        }
+                    }
+                    Ok(None) | Err(RecvError::Closed) => return,
+                    // catch up from the history, like a reconnecting client would
+                    Err(RecvError::Lagged(_)) => break,
+                }
+            }
+        }
+    };
+
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
+async fn metrics(State(monitor): State<RosMonitor>) -> impl IntoResponse {
+    ([(header::CONTENT_TYPE, "text/plain; version=0.0.4")], metrics::render(&monitor))
+}
@@ -0,0 +1,50 @@
+use bitcode::{Decode, Encode};
+use serde::{Deserialize, Serialize};
+
+use crate::state::RosState;
+
+// Bumped on any change to the serialized `Snapshot` or `RosState`.
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+// The whole graph at one point in time, with where it was discovered, to be
+// saved and reloaded later or on another machine.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Encode, Decode)]
+#[serde(tag = "type", rename = "snapshot")]
+pub struct Snapshot {
+    pub version: u32,
+    // milliseconds since the epoch
+    pub ts: u64,
+    pub host: String,
+    pub domain_id: u32,
+    pub state: RosState,
+}
+
+impl Snapshot {
+    // A snapshot of `state` taken on this machine, in the domain set by `ROS_DOMAIN_ID`.
+    pub fn new(ts: u64, state: RosState) -> Self {
+        Self { version: SNAPSHOT_VERSION, ts, host: hostname(), domain_id: domain_id(), state }
+    }
+
+    pub fn is_supported(&self) -> bool {
+        self.version <= SNAPSHOT_VERSION
+    }
+}
+
+pub fn domain_id() -> u32 {
+    std::env::var("ROS_DOMAIN_ID").ok().and_then(|id| id.parse().ok()).unwrap_or(0)
+}
+
+#[cfg(unix)]
+pub fn hostname() -> String {
+    let mut buffer = [0u8; 256];
+    if unsafe { libc::gethostname(buffer.as_mut_ptr() as *mut libc::c_char, buffer.len()) } != 0 {
+        return String::new();
+    }
+    let length = buffer.iter().position(|&byte| byte == 0).unwrap_or(buffer.len());
+    String::from_utf8_lossy(&buffer[..length]).into_owned()
+}
+
+#[cfg(not(unix))]
+pub fn hostname() -> String {
+    std::env::var("COMPUTERNAME").unwrap_or_default()
+}
@@ -1,23 +1,31 @@
-use std::collections::HashMap;
+use std::collections::BTreeMap;
+
+use bitcode::{Decode, Encode};
+use serde::{Deserialize, Serialize};
 
 use crate::types;
 
-#[derive(Debug, Default, Clone)]
+// Maps are ordered, so that iterating, events and serialized states are reproducible.
+// Nodes are ordered by namespace then name, as keyed, wherever they're listed,
+// so that the nodes of a namespace stay together.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize, Encode, Decode)]
 pub struct RosState {
-    pub nodes: HashMap<(String, String), types::NodeProperties>,
-    pub topics: HashMap<String, types::TopicProperties>,
-    pub services: HashMap<String, types::ServiceProperties>,
+    // keyed by (namespace, name), serialized as a list since a tuple can't be a JSON object key
+    #[serde(with = "node_list")]
+    pub nodes: BTreeMap<(String, String), types::NodeProperties>,
+    pub topics: BTreeMap<String, types::TopicProperties>,
+    pub services: BTreeMap<String, types::ServiceProperties>,
 }
 
 impl RosState {
     pub fn update(&mut self, event: types::DiscoveryEvent) {
         match event {
-            types::DiscoveryEvent::Ping => {}
+            types::DiscoveryEvent::Ping | types::DiscoveryEvent::Unknown => {}
             types::DiscoveryEvent::NodeAdded { name, namespace, properties } => {
-                self.nodes.insert((name, namespace), properties);
+                self.nodes.insert((namespace, name), properties);
             }
             types::DiscoveryEvent::NodeRemoved { name, namespace } => {
-                self.nodes.remove(&(name, namespace));
+                self.nodes.remove(&(namespace, name));
             }
             types::DiscoveryEvent::TopicAdded { name, properties } => {
                 self.topics.insert(name, properties);
@@ -34,68 +42,146 @@ impl RosState {
         }
     }
 
+    // The events turning `prev` into this state: removals first, then additions,
+    // each ordered by kind (nodes, topics, services) and name.
     pub fn changes(&self, prev: &Self) -> Vec<types::DiscoveryEvent> {
         let mut events = vec![];
 
-        for node_key in prev.nodes.keys() {
-            if !self.nodes.contains_key(node_key) {
-                events.push(types::DiscoveryEvent::NodeRemoved {
-                    name: node_key.0.clone(),
-                    namespace: node_key.1.clone(),
-                });
-            }
+        for (namespace, name) in prev.nodes.keys().filter(|key| !self.nodes.contains_key(*key)) {
+            events.push(types::DiscoveryEvent::NodeRemoved { name: name.clone(), namespace: namespace.clone() });
+        }
+        for name in prev.topics.keys().filter(|name| !self.topics.contains_key(*name)) {
+            events.push(types::DiscoveryEvent::TopicRemoved { name: name.clone() });
+        }
+        for name in prev.services.keys().filter(|name| !self.services.contains_key(*name)) {
+            events.push(types::DiscoveryEvent::ServiceRemoved { name: name.clone() });
         }
 
-        for (node_key, node) in self.nodes.iter() {
-            let prev_node = prev.nodes.get(node_key);
-            let new_node = self.nodes.get(node_key);
-            if prev_node != new_node {
+        for (key, node) in self.nodes.iter() {
+            if prev.nodes.get(key) != Some(node) {
                 events.push(types::DiscoveryEvent::NodeAdded {
-                    name: node_key.0.clone(),
-                    namespace: node_key.1.clone(),
+                    name: key.1.clone(),
+                    namespace: key.0.clone(),
                     properties: node.clone(),
                 });
             }
         }
-
-        for topic_key in prev.topics.keys() {
-            if !self.topics.contains_key(topic_key) {
-                events.push(types::DiscoveryEvent::TopicRemoved {
-                    name: topic_key.clone(),
-                });
+        for (name, topic) in self.topics.iter() {
+            if prev.topics.get(name) != Some(topic) {
+                events.push(types::DiscoveryEvent::TopicAdded { name: name.clone(), properties: topic.clone() });
             }
         }
-
-        for (topic_key, topic) in self.topics.iter() {
-            let prev_topic = prev.topics.get(topic_key);
-            let new_topic = self.topics.get(topic_key);
-            if prev_topic != new_topic {
-                events.push(types::DiscoveryEvent::TopicAdded {
-                    name: topic_key.clone(),
-                    properties: topic.clone(),
-                });
+        for (name, service) in self.services.iter() {
+            if prev.services.get(name) != Some(service) {
+                events.push(types::DiscoveryEvent::ServiceAdded { name: name.clone(), properties: service.clone() });
             }
         }
 
-        for service_key in prev.services.keys() {
-            if !self.services.contains_key(service_key) {
-                events.push(types::DiscoveryEvent::ServiceRemoved {
-                    name: service_key.clone(),
-                });
-            }
+        events
+    }
+}
+
+// `nodes` as `[{"name": ..., "namespace": ..., "enclave": ..., ...}]`
+mod node_list {
+    use std::collections::BTreeMap;
+
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use crate::types::NodeProperties;
+
+    #[derive(Serialize)]
+    struct NodeRef<'a> {
+        name: &'a str,
+        namespace: &'a str,
+        #[serde(flatten)]
+        properties: &'a NodeProperties,
+    }
+
+    #[derive(Deserialize)]
+    struct Node {
+        name: String,
+        namespace: String,
+        #[serde(flatten)]
+        properties: NodeProperties,
+    }
+
+    pub fn serialize<S: Serializer>(nodes: &BTreeMap<(String, String), NodeProperties>, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(nodes.iter().map(|((namespace, name), properties)| NodeRef { name, namespace, properties }))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<BTreeMap<(String, String), NodeProperties>, D::Error> {
+        let nodes = Vec::<Node>::deserialize(deserializer)?;
+        Ok(nodes.into_iter().map(|node| ((node.namespace, node.name), node.properties)).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::name::fully_qualified_name;
+
+    fn node() -> types::NodeProperties {
+        types::NodeProperties {
+            enclave: "/".to_owned(),
+            publishers: BTreeMap::new(),
+            subscribers: BTreeMap::new(),
+            clients: BTreeMap::new(),
+            services: BTreeMap::new(),
+            participant: None,
         }
+    }
 
-        for (service_key, service) in self.services.iter() {
-            let prev_service = prev.services.get(service_key);
-            let new_service = self.services.get(service_key);
-            if prev_service != new_service {
-                events.push(types::DiscoveryEvent::ServiceAdded {
-                    name: service_key.clone(),
-                    properties: service.clone(),
-                });
-            }
+    fn topic() -> types::TopicProperties {
+        types::TopicProperties { types: vec!["std_msgs/msg/String".to_owned()], publishers: vec![], subscribers: vec![] }
+    }
+
+    fn state<'a>(nodes: impl IntoIterator<Item = (&'a str, &'a str)>, topics: impl IntoIterator<Item = &'a str>) -> RosState {
+        let mut state = RosState::default();
+        for (name, namespace) in nodes {
+            state.nodes.insert((namespace.to_owned(), name.to_owned()), node());
         }
+        for name in topics {
+            state.topics.insert(name.to_owned(), topic());
+        }
+        state
+    }
 
-        events
+    fn names(events: &[types::DiscoveryEvent]) -> Vec<String> {
+        events.iter().map(|event| match event {
+            types::DiscoveryEvent::NodeAdded { name, namespace, .. } => format!("+node {}", fully_qualified_name(namespace, name)),
+            types::DiscoveryEvent::NodeRemoved { name, namespace } => format!("-node {}", fully_qualified_name(namespace, name)),
+            types::DiscoveryEvent::TopicAdded { name, .. } => format!("+topic {}", name),
+            types::DiscoveryEvent::TopicRemoved { name } => format!("-topic {}", name),
+            event => format!("{:?}", event),
+        }).collect()
+    }
+
+    #[test]
+    fn insertion_order_does_not_matter() {
+        let nodes = [("talker", "/robot2"), ("listener", "/"), ("talker", "/robot1")];
+        let topics = ["/odom", "/chatter", "/robot1/odom"];
+        let forward = state(nodes, topics);
+        let reversed = state(nodes.into_iter().rev(), topics.into_iter().rev());
+
+        assert_eq!(serde_json::to_string(&forward).unwrap(), serde_json::to_string(&reversed).unwrap());
+        assert_eq!(bitcode::encode(&forward), bitcode::encode(&reversed));
+        assert_eq!(forward.changes(&RosState::default()), reversed.changes(&RosState::default()));
+    }
+
+    #[test]
+    fn nodes_by_namespace_then_name() {
+        let state = state([("talker", "/robot2"), ("listener", "/robot3"), ("talker", "/robot1")], []);
+        let serialized = serde_json::to_value(&state).unwrap();
+        let nodes: Vec<_> = serialized["nodes"].as_array().unwrap().iter()
+            .map(|node| fully_qualified_name(node["namespace"].as_str().unwrap(), node["name"].as_str().unwrap()))
+            .collect();
+        assert_eq!(nodes, ["/robot1/talker", "/robot2/talker", "/robot3/listener"]);
+    }
+
+    #[test]
+    fn removals_before_additions() {
+        let prev = state([("talker", "/"), ("b", "/")], ["/b", "/z"]);
+        let next = state([("a", "/"), ("talker", "/")], ["/a", "/c"]);
+        assert_eq!(names(&next.changes(&prev)), ["-node /b", "-topic /b", "-topic /z", "+node /a", "+topic /a", "+topic /c"]);
     }
 }
@@ -0,0 +1,31 @@
+use crate::filter::fully_qualified_name;
+use crate::types::{DiscoveryEvent, DiscoveryEventWrapper};
+
+// Target of the records emitted for graph changes, to enable them separately
+// from the rest of the logs (e.g. `RUST_LOG=ros_monitor_lib::graph=info`).
+pub const TARGET: &str = "ros_monitor_lib::graph";
+
+// Emits a graph change as a structured `tracing` event, which a subscriber
+// can forward to e.g. an OpenTelemetry collector alongside application traces.
+pub fn record_event(event: &DiscoveryEventWrapper) {
+    let Some((entity, change, name)) = describe(&event.event) else { return };
+    tracing::info!(target: TARGET, ts = event.ts, entity, change, name = %name, "{} {} {}", entity, name, change);
+}
+
+// Like `record_event`, with the domain the change happened in.
+pub fn record_domain_event(domain_id: u32, event: &DiscoveryEventWrapper) {
+    let Some((entity, change, name)) = describe(&event.event) else { return };
+    tracing::info!(target: TARGET, domain_id, ts = event.ts, entity, change, name = %name, "{} {} {}", entity, name, change);
+}
+
+fn describe(event: &DiscoveryEvent) -> Option<(&'static str, &'static str, String)> {
+    Some(match event {
+        DiscoveryEvent::NodeAdded { name, namespace, .. } => ("node", "added", fully_qualified_name(namespace, name)),
+        DiscoveryEvent::NodeRemoved { name, namespace } => ("node", "removed", fully_qualified_name(namespace, name)),
+        DiscoveryEvent::TopicAdded { name, .. } => ("topic", "added", name.clone()),
+        DiscoveryEvent::TopicRemoved { name } => ("topic", "removed", name.clone()),
+        DiscoveryEvent::ServiceAdded { name, .. } => ("service", "added", name.clone()),
+        DiscoveryEvent::ServiceRemoved { name } => ("service", "removed", name.clone()),
+        DiscoveryEvent::Ping | DiscoveryEvent::Unknown => return None,
+    })
+}
@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 use std::time::Duration;
 
 use bitcode::{Decode, Encode};
@@ -7,10 +7,22 @@ use serde::{Deserialize, Serialize};
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Encode, Decode)]
 pub struct DiscoveryEventWrapper {
     pub ts: u64,
+    // Increases by one with every event published by a monitor, so that events
+    // sharing a timestamp can still be told apart when replaying.
+    #[serde(default)]
+    pub seq: u64,
     #[serde(flatten)]
     pub event: DiscoveryEvent,
 }
 
+// An event from one of several ROS domains, see `domains::MultiDomainMonitor`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Encode, Decode)]
+pub struct DomainEventWrapper {
+    pub domain_id: u32,
+    #[serde(flatten)]
+    pub event: DiscoveryEventWrapper,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Encode, Decode)]
 #[serde(tag = "type")]
 #[serde(rename_all = "snake_case")]
@@ -39,15 +51,55 @@ pub enum DiscoveryEvent {
     ServiceRemoved {
         name: String,
     },
+    #[serde(other)]
+    Unknown,
+}
+
+impl DiscoveryEvent {
+    // The `type` tag used in serialized events.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Ping => "ping",
+            Self::NodeAdded { .. } => "node_added",
+            Self::NodeRemoved { .. } => "node_removed",
+            Self::TopicAdded { .. } => "topic_added",
+            Self::TopicRemoved { .. } => "topic_removed",
+            Self::ServiceAdded { .. } => "service_added",
+            Self::ServiceRemoved { .. } => "service_removed",
+            Self::Unknown => "unknown",
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Encode, Decode)]
 pub struct NodeProperties {
     pub enclave: String,
-    pub publishers: HashMap<String, String>,
-    pub subscribers: HashMap<String, String>,
-    pub clients: HashMap<String, String>,
-    pub services: HashMap<String, String>,
+    pub publishers: BTreeMap<String, String>,
+    pub subscribers: BTreeMap<String, String>,
+    pub clients: BTreeMap<String, String>,
+    pub services: BTreeMap<String, String>,
+    // best effort, see `participant`
+    #[serde(default)]
+    pub participant: Option<ParticipantInfo>,
+}
+
+// The DDS participant of a node, which is shared by the nodes of a process.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, Encode, Decode)]
+pub struct ParticipantInfo {
+    // hex, identifies the participant
+    pub guid_prefix: String,
+    // identifies the host, from a hash of its addresses
+    pub host_id: Option<u16>,
+    // only known for the nodes running on the same host as the monitor
+    pub host: Option<String>,
+    pub pid: Option<u32>,
+}
+
+impl ParticipantInfo {
+    // How to tell the host apart from others, e.g. to group nodes by machine.
+    pub fn host_label(&self) -> Option<String> {
+        self.host.clone().or_else(|| self.host_id.map(|host_id| format!("host-{:04x}", host_id)))
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Encode, Decode)]
@@ -62,7 +114,7 @@ pub struct ServiceProperties {
     pub types: Vec<String>,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Encode, Decode)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, Encode, Decode)]
 pub struct PubSubProperties {
     pub node_name: String,
     pub node_namespace: String,
@@ -70,7 +122,7 @@ pub struct PubSubProperties {
     pub qos_profile: QosProfile,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Encode, Decode)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, Encode, Decode)]
 pub struct QosProfile {
     pub history: HistoryPolicy,
     pub depth: usize,
@@ -82,7 +134,24 @@ pub struct QosProfile {
     pub liveliness_lease_duration: Duration,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Encode, Decode)]
+impl QosProfile {
+    // Whether a subscription requesting `subscriber` gets messages from a publisher
+    // offering this profile, following the ROS 2 QoS compatibility rules. Zero
+    // durations are treated as unspecified, i.e. infinite.
+    pub fn is_compatible_with(&self, subscriber: &QosProfile) -> bool {
+        fn offered_within(offered: Duration, requested: Duration) -> bool {
+            requested.is_zero() || (!offered.is_zero() && offered <= requested)
+        }
+
+        !matches!((self.reliability, subscriber.reliability), (ReliabilityPolicy::BestEffort, ReliabilityPolicy::Reliable))
+            && !matches!((self.durability, subscriber.durability), (DurabilityPolicy::Volatile, DurabilityPolicy::TransientLocal))
+            && !matches!((self.liveliness, subscriber.liveliness), (LivelinessPolicy::Automatic, LivelinessPolicy::ManualByTopic))
+            && offered_within(self.deadline, subscriber.deadline)
+            && offered_within(self.liveliness_lease_duration, subscriber.liveliness_lease_duration)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, Encode, Decode)]
 pub enum HistoryPolicy {
     KeepAll,
     KeepLast,
@@ -90,7 +159,7 @@ pub enum HistoryPolicy {
     Unknown,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Encode, Decode)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, Encode, Decode)]
 pub enum ReliabilityPolicy {
     BestEffort,
     Reliable,
@@ -99,7 +168,7 @@ pub enum ReliabilityPolicy {
     Unknown,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Encode, Decode)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, Encode, Decode)]
 pub enum DurabilityPolicy {
     TransientLocal,
     Volatile,
@@ -108,7 +177,7 @@ pub enum DurabilityPolicy {
     Unknown,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Encode, Decode)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, Encode, Decode)]
 pub enum LivelinessPolicy {
     Automatic,
     ManualByNode,